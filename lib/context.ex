@@ -7,18 +7,88 @@ defmodule ExGpgme.Context do
 
   @type t :: Context
   @type path :: binary()
-  @type reason :: :foo
+  @typedoc """
+  Errors reported by gpgme. `code` names the failure (e.g.
+  `:bad_passphrase`, `:no_pubkey`, `:unusable_pubkey`, `:decrypt_failed`,
+  `:not_found`), `source` the component that raised it (e.g. `:gpgme`,
+  `:gpg_agent`) and `message` is gpgme's human readable description.
+
+  Arguments naming something that is not supported, e.g. an unknown
+  flag, fail with `code: :invalid_value` and `source: :user`.
+  """
+  @type reason :: %{code: atom(), source: atom(), message: binary()}
+  @typedoc """
+  A passphrase, or `:provider` to ask the context's
+  `ExGpgme.PassphraseProvider`, which is also what operations given no
+  passphrase do. Without a provider, operations that need a passphrase
+  then fail with `:no_passphrase`.
+  """
+  @type passphrase :: binary() | :provider
+  @type recipients :: Key.t() | binary() | [Key.t() | binary()]
+  @type encrypt_flag ::
+          :always_trust
+          | :no_encrypt_to
+          | :prepare
+          | :expect_sign
+          | :no_compress
+          | :symmetric
+          | :throw_keyids
+  @type export_mode :: :minimal | :secret | :extern | :ssh | :pkcs12
+  @type create_key_flag :: :sign | :encrypt | :cert | :auth | :no_password | :force
+
+  @type protocol :: :openpgp | :cms
+  @type pinentry_mode :: :default | :ask | :cancel | :error | :loopback
+  @type option ::
+          :armor
+          | :text_mode
+          | :pinentry_mode
+          | :offline
+          | :include_certs
+          | :key_list_mode
+          | :engine_path
+          | :home
 
   defstruct home: nil,
             path: nil,
             version: nil,
             protocol: nil,
+            armor: true,
+            text_mode: true,
+            pinentry_mode: :loopback,
+            offline: false,
+            include_certs: :default,
+            key_list_mode: [:local],
             ref: nil
 
-  @spec create(path()) :: {:ok, t()} | {:error, reason()}
+  @defaults [armor: true, text_mode: true, pinentry_mode: :loopback]
+
+  defguardp is_passphrase(passphrase) when is_binary(passphrase) or passphrase == :provider
+
+  @spec create(keyword()) :: {:ok, t()} | {:error, reason()}
   @doc """
   Create a new Gpgme context to work with. Pass it the home directory
-  path, or have it use the current users $HOME/.gnupg
+  path, or have it use `$GNUPGHOME` or the current users $HOME/.gnupg
+
+  ## Options
+
+    * `:home` - the GnuPG home directory, `:path` is accepted as well
+    * `:protocol` - `:openpgp` (default) to work with gpg, or `:cms` to
+      work with X.509 certificates and CMS (S/MIME) messages through
+      gpgsm. The functions of this module then import, list, sign,
+      verify, encrypt and decrypt in that format.
+    * `:armor` - produce ASCII armored output (default `true`). Set to
+      `false` to get compact binary OpenPGP packets instead.
+    * `:text_mode` - canonical text mode for signatures (default `true`)
+    * `:pinentry_mode` - how gpg-agent asks for passphrases, one of
+      `:default`, `:ask`, `:cancel`, `:error` or `:loopback` (default).
+      Passphrases given to the functions of this module, or answered by
+      a `ExGpgme.PassphraseProvider`, are only used with `:loopback`.
+    * `:offline` - do not access the network, e.g. for CRL checks
+      (default `false`)
+    * `:include_certs` - number of certificates to include in S/MIME
+      signatures, or `:default`
+    * `:key_list_mode` - default key listing modes, see `list_keys/2`
+    * `:engine_path` - the gpg executable to use
 
   ## Example
 
@@ -27,47 +97,244 @@ defmodule ExGpgme.Context do
       true
 
   """
-  def create(path: path) when is_binary(path) do
-    case ExGpgme.Native.context_create(:openpgp, path) do
-      {:ok, ref} ->
-        case ExGpgme.Native.context_info(ref) do
-          {:ok, info} ->
-            context = %Context{
-              home: info.home,
-              path: info.path,
-              version: info.version,
-              protocol: info.protocol,
-              ref: ref
-            }
-
-            {:ok, context}
-        end
+  def create(opts \\ []) when is_list(opts) do
+    home = opts[:home] || opts[:path] || default_home()
+    {protocol, opts} = Keyword.pop(opts, :protocol, :openpgp)
+    opts = Keyword.merge(@defaults, Keyword.drop(opts, [:home, :path]))
 
-      error ->
-        error
+    case ExGpgme.Native.context_create(protocol, home, opts) do
+      {:ok, ref} -> from_ref(ref)
+      error -> error
     end
   end
 
-  @spec list_keys(t()) :: [Key.t()]
+  @spec set_armor(t(), boolean()) :: {:ok, t()}
   @doc """
-  List all keys for a Context.
+  Switch between ASCII armored and binary output on an existing context.
   """
-  def list_keys(%Context{} = context) do
-    case ExGpgme.Native.key_list(context.ref) do
-      {:ok, keys} -> Enum.map(keys, fn ref -> Key.from(ref) end)
+  def set_armor(%Context{} = context, armor) when is_boolean(armor) do
+    :ok = ExGpgme.Native.context_set_armor(context.ref, armor)
+    {:ok, %Context{context | armor: armor}}
+  end
+
+  @spec options(t()) :: {:ok, map()}
+  @doc """
+  Read the current options of the context, see `create/1`.
+  """
+  def options(%Context{} = context) do
+    ExGpgme.Native.context_options(context.ref)
+  end
+
+  @spec set_option(t(), option(), term()) :: {:ok, t()} | {:error, reason()}
+  @doc """
+  Change one of the options of `create/1` on an existing context and
+  return the updated context.
+
+  ## Example
+
+      {:ok, context} = ExGpgme.Context.set_option(context, :pinentry_mode, :cancel)
+
+  """
+  def set_option(%Context{} = context, option, value) when is_atom(option) do
+    case ExGpgme.Native.context_set_option(context.ref, option, value) do
+      :ok -> from_ref(context.ref)
       error -> error
     end
   end
 
-  @spec encrypt(t(), Key.t(), binary()) :: {:ok, binary()} | {:error, atom()}
+  @spec set_passphrase_provider(t(), GenServer.server(), keyword()) :: :ok | {:error, reason()}
   @doc """
-  Encrypt some passed string with the given Key.
+  Route the passphrase requests of operations that are given no
+  passphrase, or `:provider`, to `provider`, see
+  `ExGpgme.PassphraseProvider`. Fails with `code: :not_found` if
+  `provider` is not a running process.
+
+  ## Options
+
+    * `:timeout` - milliseconds to wait for each answer (default `5000`)
+
+  """
+  def set_passphrase_provider(%Context{} = context, provider, opts \\ []) do
+    timeout = Keyword.get(opts, :timeout, 5_000)
+
+    case GenServer.whereis(provider) do
+      pid when is_pid(pid) ->
+        ExGpgme.Native.context_set_passphrase_provider(context.ref, pid, timeout)
+
+      _ ->
+        {:error,
+         %{code: :not_found, source: :ex_gpgme, message: "no passphrase provider process"}}
+    end
+  end
+
+  @spec clear_passphrase_provider(t()) :: :ok
+  @doc """
+  Stop asking the passphrase provider of the context.
+  """
+  def clear_passphrase_provider(%Context{} = context) do
+    ExGpgme.Native.context_clear_passphrase_provider(context.ref)
+  end
+
+  @spec list_keys(t(), keyword()) ::
+          [Key.t()] | {:truncated, [Key.t()]} | {:error, reason()}
+  @doc """
+  List the keys for a Context.
+
+  ## Options
+
+    * `:patterns` - only list keys matching these patterns, e.g. email
+      addresses, fingerprints or a domain such as `"@mcbar.dev"`. A
+      single pattern may be given as a binary.
+    * `:secret_only` - only list keys with a secret part (default `false`)
+    * `:mode` - key listing modes, any of `:local`, `:extern`, `:sigs`,
+      `:sig_notations`, `:with_secret`, `:with_tofu`, `:validate`,
+      `:ephemeral` and `:locate`. Defaults to the `:key_list_mode` of
+      the context, `[:local]` unless configured otherwise.
+
+  If gpg stops listing early, e.g. because a keyserver limited the
+  result, the keys found so far are returned as `{:truncated, keys}`.
+  """
+  def list_keys(%Context{} = context, opts \\ []) do
+    patterns = opts |> Keyword.get(:patterns, []) |> List.wrap()
+    secret_only = Keyword.get(opts, :secret_only, false)
+    mode = Keyword.get(opts, :mode, context.key_list_mode)
+
+    case ExGpgme.Native.key_list(context.ref, patterns, secret_only, mode) do
+      {:ok, keys, false} -> Enum.map(keys, &Key.from/1)
+      {:ok, keys, true} -> {:truncated, Enum.map(keys, &Key.from/1)}
+      error -> error
+    end
+  end
+
+  @spec stream_keys(t(), keyword()) :: Enumerable.t()
+  @doc """
+  Lazily list keys, fetching them from gpgme in batches. Takes the same
+  options as `list_keys/2` plus `:batch_size` (default 100).
+
+  The listing runs on a separate gpgme context, so only one batch is held
+  in memory at a time and the given context stays usable while the stream
+  is consumed. Errors are raised as `ExGpgme.Error`. If gpgme truncates
+  the listing, the keys found are emitted and then an `ExGpgme.Error`
+  with `code: :truncated` is raised.
+
+  ## Example
+
+      context
+      |> ExGpgme.Context.stream_keys(patterns: "@mcbar.dev")
+      |> Stream.filter(& &1.can_encrypt)
+      |> Enum.take(20)
+
+  """
+  def stream_keys(%Context{} = context, opts \\ []) do
+    patterns = opts |> Keyword.get(:patterns, []) |> List.wrap()
+    secret_only = Keyword.get(opts, :secret_only, false)
+    mode = Keyword.get(opts, :mode, context.key_list_mode)
+    batch_size = Keyword.get(opts, :batch_size, 100)
+
+    Stream.resource(
+      fn ->
+        context.ref
+        |> ExGpgme.Native.key_stream(patterns, secret_only, mode, batch_size)
+        |> stream_result()
+      end,
+      fn
+        {:done, stream} ->
+          {:halt, {:done, stream}}
+
+        {:truncated, _stream} ->
+          raise ExGpgme.Error,
+            code: :truncated,
+            source: :ex_gpgme,
+            message: "the key listing was truncated"
+
+        stream ->
+          case ExGpgme.Native.key_stream_next(stream, batch_size) do
+            {:done, keys, false} -> {Enum.map(keys, &Key.from/1), {:done, stream}}
+            {:done, keys, true} -> {Enum.map(keys, &Key.from/1), {:truncated, stream}}
+            result -> {result |> stream_result() |> Enum.map(&Key.from/1), stream}
+          end
+      end,
+      fn
+        {_, stream} -> ExGpgme.Native.key_stream_close(stream)
+        stream -> ExGpgme.Native.key_stream_close(stream)
+      end
+    )
+  end
+
+  @spec encrypt(t(), recipients(), binary(), [encrypt_flag() | {:passphrase, passphrase()}]) ::
+          {:ok, binary()} | {:error, reason()}
+  @doc """
+  Encrypt some passed string for one or more recipients, given either as
+  `%ExGpgme.Key{}` structs or fingerprints.
+
+  ## Options
+
+    * `:always_trust` - encrypt even if the recipient keys are not trusted
+    * `:no_encrypt_to` - ignore `encrypt-to` keys from the gpg configuration
+    * `:prepare`, `:expect_sign` - used for preparing a later signing step
+    * `:no_compress` - do not compress the plaintext
+    * `:symmetric` - additionally encrypt with `passphrase`
+    * `:throw_keyids` - hide the recipients' key ids
+    * `passphrase: binary` - passphrase for `:symmetric` encryption
+
+  Recipient keys have to be valid unless `:always_trust` is given.
+  """
+  def encrypt(%Context{} = context, recipients, data, opts \\ []) when is_binary(data) do
+    {flags, passphrase} = flag_options(opts)
+
+    ExGpgme.Native.context_encrypt(
+      context.ref,
+      passphrase,
+      fingerprints(recipients),
+      data,
+      flags
+    )
+  end
+
+  @spec encrypt_stream(
+          t(),
+          recipients(),
+          Enumerable.t() | Path.t(),
+          [encrypt_flag() | {:passphrase, passphrase()}]
+        ) :: Enumerable.t()
+  @doc """
+  Lazily encrypt an `Enumerable` of binaries, or the file at the given
+  path, for the given recipients. Returns a stream of ciphertext chunks.
+  Takes the same options as `encrypt/4`.
+
+  Input is handed to gpgme one chunk at a time, so memory use does not
+  depend on the size of the data. The stream runs on a separate gpgme
+  context, so the given context stays usable while it is consumed.
+  Errors are raised as `ExGpgme.Error`.
+
+  ## Example
+
+      context
+      |> ExGpgme.Context.encrypt_stream([key], "dump.sql", [:always_trust])
+      |> Stream.into(File.stream!("dump.sql.gpg"))
+      |> Stream.run()
+
+  """
+  def encrypt_stream(%Context{} = context, recipients, source, opts \\ []) do
+    {flags, passphrase} = flag_options(opts)
+    fingerprints = fingerprints(recipients)
+
+    run_stream(source, fn ->
+      ExGpgme.Native.stream_encrypt(context.ref, passphrase, fingerprints, flags)
+    end)
+  end
+
+  @spec decrypt_stream(t(), passphrase(), Enumerable.t() | Path.t()) :: Enumerable.t()
+  @doc """
+  Lazily decrypt an `Enumerable` of binaries, or the file at the given
+  path, with the given passphrase. Returns a stream of plaintext chunks,
+  see `encrypt_stream/4`.
   """
-  def encrypt(%Context{} = context, %Key{} = key, data) when is_binary(data) do
-    ExGpgme.Native.context_encrypt(context.ref, key.fingerprint, data)
+  def decrypt_stream(%Context{} = context, passphrase, source) when is_passphrase(passphrase) do
+    run_stream(source, fn -> ExGpgme.Native.stream_decrypt(context.ref, passphrase) end)
   end
 
-  @spec encrypt_symmetric(t(), binary(), binary()) :: {:ok, binary()} | {:error, atom()}
+  @spec encrypt_symmetric(t(), passphrase(), binary()) :: {:ok, binary()} | {:error, reason()}
   @doc """
   Encrypt some passed string with the given Key.
   """
@@ -76,16 +343,180 @@ defmodule ExGpgme.Context do
     ExGpgme.Native.context_encrypt_symmetric(context.ref, passphrase, data)
   end
 
-  @spec decrypt(t(), binary(), binary()) :: {:ok, binary()} | {:error, atom()}
+  @spec decrypt(t(), passphrase(), binary()) :: {:ok, binary()} | {:error, reason()}
   @doc """
   Decrypt some passed string with the given passphrase.
   """
   def decrypt(%Context{} = context, passphrase, data)
-      when is_binary(data) and is_binary(passphrase) do
+      when is_binary(data) and is_passphrase(passphrase) do
     ExGpgme.Native.context_decrypt(context.ref, passphrase, data)
   end
 
-  @spec import_key(t(), binary()) :: {:ok, map()} | {:error, atom()}
+  @spec encrypt_sign(
+          t(),
+          recipients(),
+          binary(),
+          [encrypt_flag() | {:passphrase, passphrase()}]
+        ) :: {:ok, binary(), map()} | {:error, reason()}
+  @doc """
+  Sign some passed string with the signers configured on the context and
+  encrypt it for the given recipients in one go. Takes the same options
+  as `encrypt/4`, where `:passphrase` also unlocks the signers' keys.
+  """
+  def encrypt_sign(%Context{} = context, recipients, data, opts \\ [])
+      when is_binary(data) do
+    {flags, passphrase} = flag_options(opts)
+
+    ExGpgme.Native.context_encrypt_sign(
+      context.ref,
+      passphrase,
+      fingerprints(recipients),
+      data,
+      flags
+    )
+  end
+
+  @spec decrypt_verify(t(), passphrase(), binary()) :: {:ok, binary(), map()} | {:error, reason()}
+  @doc """
+  Decrypt some passed string with the given passphrase and verify the
+  signatures it contains.
+
+  The returned map holds the `recipients`, `symmetric_algorithm`,
+  `filename` and `unsigned` fields of the decryption along with the
+  `signatures` found, in the same shape as `verify/3`.
+  """
+  def decrypt_verify(%Context{} = context, passphrase, data)
+      when is_binary(data) and is_passphrase(passphrase) do
+    ExGpgme.Native.context_decrypt_verify(context.ref, passphrase, data)
+  end
+
+  @spec sign(t(), binary(), keyword()) :: {:ok, binary(), map()} | {:error, reason()}
+  @doc """
+  Sign some passed string with the signers configured on the context.
+
+  ## Options
+
+    * `:mode` - one of `:normal` (default), `:detached` or `:clear`
+    * `:passphrase` - passphrase unlocking the signers' secret keys
+
+  Returns the signature together with a map listing the `new_signatures`
+  that were created and any `invalid_signers`.
+  """
+  def sign(%Context{} = context, data, opts \\ []) when is_binary(data) do
+    mode = Keyword.get(opts, :mode, :normal)
+    passphrase = passphrase(opts)
+    ExGpgme.Native.context_sign(context.ref, passphrase, mode, data)
+  end
+
+  @spec verify(t(), binary(), keyword()) ::
+          {:ok, binary() | :none, [map()]} | {:error, reason()}
+  @doc """
+  Verify a normal, clear-text or detached signature.
+
+  For normal and clear-text signatures the signed plaintext is returned
+  alongside the signatures. To check a detached signature pass the signed
+  data as `:signed_text`, in which case the plaintext is `:none`.
+
+  By default a successful return only means the data could be processed.
+  Callers should then inspect each signature's `status`, `summary` and
+  `validity` to decide whether it was made by a trusted key, e.g. by
+  requiring `:valid` in the summary.
+
+  ## Options
+
+    * `:signed_text` - the signed data of a detached signature
+    * `:require_validity` - one of `:marginal`, `:full` or `:ultimate`.
+      Fails with `code: :untrusted_signature` unless there is at least
+      one signature and every signature is good and made by a key of at
+      least this validity. The validity follows the trust model of the
+      keyring, so with GnuPG's TOFU model it reflects the TOFU policy,
+      while conflicts only show up as `:tofu_conflict` in the summary.
+  """
+  def verify(%Context{} = context, signature, opts \\ []) when is_binary(signature) do
+    result =
+      case Keyword.get(opts, :signed_text) do
+        nil -> ExGpgme.Native.context_verify(context.ref, signature)
+        text -> ExGpgme.Native.context_verify_detached(context.ref, signature, text)
+      end
+
+    with {:ok, _plaintext, signatures} <- result,
+         :ok <- require_validity(signatures, opts[:require_validity]) do
+      result
+    end
+  end
+
+  @spec encrypt_file(t(), recipients(), Path.t(), Path.t(), [
+          encrypt_flag() | {:passphrase, passphrase()}
+        ]) :: :ok | {:error, reason()}
+  @doc """
+  Encrypt the file at `source` for the given recipients and write the
+  ciphertext to `destination`. gpgme reads and writes the files itself,
+  so no data is copied through the VM. Takes the same options as
+  `encrypt/4`.
+
+  `destination` must not exist yet and is removed again if the operation
+  fails. Files that cannot be opened fail with `:not_found`,
+  `:permission_denied` or `:already_exists`, and `destination` being
+  `source` with `:invalid_value`.
+  """
+  def encrypt_file(%Context{} = context, recipients, source, destination, opts \\ []) do
+    {flags, passphrase} = flag_options(opts)
+
+    ExGpgme.Native.context_encrypt_file(
+      context.ref,
+      passphrase,
+      fingerprints(recipients),
+      source,
+      destination,
+      flags
+    )
+  end
+
+  @spec decrypt_file(t(), passphrase(), Path.t(), Path.t()) :: :ok | {:error, reason()}
+  @doc """
+  Decrypt the file at `source` with the given passphrase and write the
+  plaintext to `destination`, see `encrypt_file/5`.
+  """
+  def decrypt_file(%Context{} = context, passphrase, source, destination)
+      when is_passphrase(passphrase) do
+    ExGpgme.Native.context_decrypt_file(context.ref, passphrase, source, destination)
+  end
+
+  @spec sign_file(t(), Path.t(), Path.t(), keyword()) :: {:ok, map()} | {:error, reason()}
+  @doc """
+  Sign the file at `source` and write the signature to `destination`.
+  Takes the same options as `sign/3` and returns the same result map.
+  """
+  def sign_file(%Context{} = context, source, destination, opts \\ []) do
+    mode = Keyword.get(opts, :mode, :normal)
+    passphrase = passphrase(opts)
+    ExGpgme.Native.context_sign_file(context.ref, passphrase, mode, source, destination)
+  end
+
+  @spec verify_file(t(), Path.t(), Path.t() | nil, keyword()) ::
+          {:ok, [map()]} | {:error, reason()}
+  @doc """
+  Verify the signature in the file at `signature`.
+
+  For normal and clear-text signatures the signed plaintext is written to
+  `destination`, or discarded if it is `nil`. To check a detached
+  signature pass the path of the signed data as `:signed_file`.
+
+  The returned signatures have the same shape as in `verify/3` and
+  `:require_validity` is checked the same way.
+  """
+  def verify_file(%Context{} = context, signature, destination, opts \\ []) do
+    signed = Keyword.get(opts, :signed_file) || :none
+    destination = destination || :none
+
+    with {:ok, signatures} = result <-
+           ExGpgme.Native.context_verify_file(context.ref, signature, signed, destination),
+         :ok <- require_validity(signatures, opts[:require_validity]) do
+      result
+    end
+  end
+
+  @spec import_key(t(), binary()) :: {:ok, map()} | {:error, reason()}
   @doc """
   Decrypt some passed string with the given passphrase.
   """
@@ -93,18 +524,388 @@ defmodule ExGpgme.Context do
     ExGpgme.Native.context_import(context.ref, data)
   end
 
-  @spec find_key(t(), binary()) :: {:ok, Key.t()} | {:error, atom()}
+  @spec import_pkcs12(t(), binary(), passphrase()) :: {:ok, map()} | {:error, reason()}
+  @doc """
+  Import an S/MIME identity, a certificate together with its secret key
+  and usually its issuers, from a PKCS#12 (`.p12`) bundle into a `:cms`
+  context. `passphrase` unlocks the bundle and protects the imported
+  secret key. Pass `:provider` to ask the context's
+  `ExGpgme.PassphraseProvider` instead.
+
+  Returns the same map as `import_key/2`.
+  """
+  def import_pkcs12(%Context{} = context, data, passphrase)
+      when is_binary(data) and is_passphrase(passphrase) do
+    ExGpgme.Native.context_import_pkcs12(context.ref, passphrase, data)
+  end
+
+  @spec export_pkcs12(t(), Key.t() | binary(), passphrase()) ::
+          {:ok, binary()} | {:error, reason()}
+  @doc """
+  Export the certificate and secret key of a `:cms` context as a PKCS#12
+  bundle. `passphrase` unlocks the secret key and protects the bundle;
+  pass `:provider` to ask the context's `ExGpgme.PassphraseProvider` instead.
+  Disable `armor` on the context to get a binary `.p12` file.
+  """
+  def export_pkcs12(%Context{} = context, key, passphrase) when is_passphrase(passphrase) do
+    export_keys(context, key, [:secret, :pkcs12, passphrase: passphrase])
+  end
+
+  @spec find_key(t(), binary()) :: {:ok, Key.t()} | {:error, reason()}
   @doc """
   Decrypt some passed string with the given passphrase.
   """
   def find_key(%Context{} = context, query) do
     case ExGpgme.Native.context_find_key(context.ref, query) do
-      :not_found -> {:error, :not_found}
       {:ok, ref} -> {:ok, ExGpgme.Key.from(ref)}
+      error -> error
     end
   end
 
-  @spec signature_notations(t()) :: {:ok, list()} | {:error, atom()}
+  @spec certificate_chain(t(), Key.t()) :: {:ok, [Key.t()]} | {:error, reason()}
+  @doc """
+  Follow the `chain_id` of an X.509 certificate up to its root. Returns
+  the certificate itself first and the root certificate last. Fails with
+  `:not_found` if an issuer's certificate is not in the keyring.
+
+  ## Example
+
+      {:ok, [certificate, issuer | _]} = ExGpgme.Context.certificate_chain(context, key)
+      issuer.user_ids |> hd() |> Map.get(:dn)
+
+  """
+  def certificate_chain(%Context{} = context, %Key{} = key) do
+    certificate_chain(context, key, [key])
+  end
+
+  defp certificate_chain(context, %Key{chain_id: issuer} = key, chain) do
+    cond do
+      issuer in [:none, key.fingerprint] ->
+        {:ok, Enum.reverse(chain)}
+
+      # A cross-certified issuer would otherwise be walked forever.
+      Enum.any?(chain, &(&1.fingerprint == issuer)) ->
+        {:ok, Enum.reverse(chain)}
+
+      true ->
+        case find_key(context, issuer) do
+          {:ok, issuer} -> certificate_chain(context, issuer, [issuer | chain])
+          error -> error
+        end
+    end
+  end
+
+  @spec generate_key(t(), binary(), keyword()) :: {:ok, binary(), Key.t()} | {:error, reason()}
+  @doc """
+  Generate a new primary key for `user_id`, e.g.
+  `"Foo McBar <foo@mcbar.dev>"`.
+
+  ## Options
+
+    * `:algorithm` - e.g. `"rsa3072"`, `"ed25519"` or `"future-default"`
+      (default `"default"`)
+    * `:expires` - lifetime in seconds, `0` for gpg's default (default) or
+      `:never`
+    * `:flags` - list of `:sign`, `:encrypt`, `:cert`, `:auth` to choose
+      the key's capabilities, `:no_password` to store the secret key
+      unprotected and `:force` to create a key for an existing user id
+    * `:passphrase` - passphrase protecting the new secret key
+
+  Returns the fingerprint of the new key along with the key itself.
+  """
+  def generate_key(%Context{} = context, user_id, opts \\ []) when is_binary(user_id) do
+    {algorithm, expires, flags, passphrase} = create_key_options(opts)
+
+    context.ref
+    |> ExGpgme.Native.context_generate_key(passphrase, user_id, algorithm, expires, flags)
+    |> created_key()
+  end
+
+  @spec add_subkey(t(), Key.t(), binary(), keyword()) ::
+          {:ok, binary(), Key.t()} | {:error, reason()}
+  @doc """
+  Add a subkey using `algorithm` to an existing key. Takes the same
+  `:expires`, `:flags` and `:passphrase` options as `generate_key/3`,
+  where `:passphrase` has to unlock the primary key.
+
+  Returns the fingerprint reported by gpgme along with the updated key.
+  """
+  def add_subkey(%Context{} = context, %Key{} = key, algorithm, opts \\ [])
+      when is_binary(algorithm) do
+    {_, expires, flags, passphrase} = create_key_options(opts)
+
+    context.ref
+    |> ExGpgme.Native.context_add_subkey(passphrase, key.ref, algorithm, expires, flags)
+    |> created_key()
+  end
+
+  @spec export_keys(t(), recipients(), [export_mode() | {:passphrase, passphrase()}]) ::
+          {:ok, binary()} | {:error, reason()}
+  @doc """
+  Export keys, given either as `%ExGpgme.Key{}` structs or patterns such
+  as fingerprints or email addresses. The output is ASCII armored or
+  binary depending on the context's `armor` setting.
+
+  ## Modes
+
+    * `:minimal` - strip all signatures except the latest self-signatures
+    * `:secret` - export the secret keys, unlocked with `passphrase:`
+    * `:extern` - send the keys to the configured keyserver instead, in
+      which case the returned data is empty
+    * `:ssh` - export the authentication subkey in OpenSSH format
+    * `:pkcs12` - export X.509 secret keys as PKCS#12
+
+  An empty list of keys fails with `:invalid_value`, use `export_all/2`
+  to export every key.
+  """
+  def export_keys(%Context{} = context, keys, mode \\ []) do
+    {mode, passphrase} = flag_options(mode)
+    ExGpgme.Native.context_export(context.ref, passphrase, fingerprints(keys), mode)
+  end
+
+  @spec export_all(t(), [export_mode() | {:passphrase, passphrase()}]) ::
+          {:ok, binary()} | {:error, reason()}
+  @doc """
+  Export every key in the keyring, e.g. for a backup. Takes the same
+  modes as `export_keys/3`.
+  """
+  def export_all(%Context{} = context, mode \\ []) do
+    {mode, passphrase} = flag_options(mode)
+    ExGpgme.Native.context_export(context.ref, passphrase, :all, mode)
+  end
+
+  @spec delete_key(t(), Key.t() | binary(), [:allow_secret | :force]) :: :ok | {:error, reason()}
+  @doc """
+  Delete a key, given as `%ExGpgme.Key{}` or fingerprint, from the keyring.
+
+  ## Options
+
+    * `:allow_secret` - also delete the secret key. Without it, deleting a
+      key with a secret part fails with a `:conflict` error.
+    * `:force` - do not ask for confirmation
+
+  Fails with `:not_found` if there is no such key and with
+  `:invalid_value` if given anything but a single key.
+  """
+  def delete_key(context, key, opts \\ [])
+
+  def delete_key(%Context{} = context, %Key{fingerprint: fingerprint}, opts) do
+    delete_key(context, fingerprint, opts)
+  end
+
+  def delete_key(%Context{} = context, fingerprint, opts) when is_binary(fingerprint) do
+    ExGpgme.Native.context_delete_key(context.ref, fingerprint, opts)
+  end
+
+  def delete_key(%Context{}, _key, _opts) do
+    {:error, %{code: :invalid_value, source: :user, message: "expected a single key"}}
+  end
+
+  @spec sign_key(t(), Key.t(), :all | [binary()], keyword()) :: {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Certify the user ids of another key with the signers configured on the
+  context. `user_ids` is either `:all` or a list of user id strings,
+  e.g. `["Foo McBar <foo@mcbar.dev>"]`.
+
+  ## Options
+
+    * `:local` - make a local, non-exportable signature
+    * `:expires` - lifetime of the signature in seconds or `:never`
+      (default `0`, gpg's default)
+    * `:force` - sign even if a signature already exists
+    * `:passphrase` - passphrase unlocking the signers' secret keys
+
+  Returns the updated key. The new certifications are listed in the
+  `signatures` of its `user_ids`.
+  """
+  def sign_key(%Context{} = context, %Key{} = key, user_ids \\ :all, opts \\ []) do
+    flags = Enum.filter([:local, :force], &Keyword.get(opts, &1, false))
+
+    context.ref
+    |> ExGpgme.Native.context_sign_key(
+      passphrase(opts),
+      key.ref,
+      user_id_list(user_ids),
+      Keyword.get(opts, :expires, 0),
+      flags
+    )
+    |> updated_key()
+  end
+
+  @spec revoke_signature(t(), Key.t(), Key.t(), keyword()) :: {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Revoke the certifications `signer` made on `key`.
+
+  ## Options
+
+    * `:user_ids` - `:all` (default) or the list of user ids to revoke
+      the certification for
+    * `:passphrase` - passphrase unlocking the signer's secret key
+
+  Returns the updated key.
+  """
+  def revoke_signature(%Context{} = context, %Key{} = key, %Key{} = signer, opts \\ []) do
+    context.ref
+    |> ExGpgme.Native.context_revoke_signature(
+      passphrase(opts),
+      key.ref,
+      signer.ref,
+      user_id_list(Keyword.get(opts, :user_ids, :all))
+    )
+    |> updated_key()
+  end
+
+  @spec set_expire(t(), Key.t(), pos_integer() | :never, keyword()) ::
+          {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Set the expiration of a key to `expires` seconds from now, or remove it
+  with `:never`.
+
+  ## Options
+
+    * `:subkeys` - change these subkeys, given as fingerprints or subkey
+      maps, instead of the primary key. `:all` changes every subkey.
+    * `:passphrase` - passphrase unlocking the primary key
+
+  Returns the updated key.
+  """
+  def set_expire(%Context{} = context, %Key{} = key, expires, opts \\ []) do
+    subkeys =
+      case Keyword.get(opts, :subkeys, []) do
+        :all -> ["*"]
+        subkeys -> Enum.map(subkeys, &subkey_fingerprint/1)
+      end
+
+    context.ref
+    |> ExGpgme.Native.context_set_expire(passphrase(opts), key.ref, expires, subkeys)
+    |> updated_key()
+  end
+
+  @spec add_uid(t(), Key.t(), binary(), keyword()) :: {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Add a user id such as `"Foo McBar <foo@mcbar.dev>"` to a key.
+  `:passphrase` unlocks the primary key. Returns the updated key.
+  """
+  def add_uid(%Context{} = context, %Key{} = key, user_id, opts \\ []) when is_binary(user_id) do
+    context.ref
+    |> ExGpgme.Native.context_add_uid(passphrase(opts), key.ref, user_id)
+    |> updated_key()
+  end
+
+  @spec revoke_uid(t(), Key.t(), binary(), keyword()) :: {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Revoke a user id of a key, given exactly as it was added. See
+  `add_uid/4`.
+  """
+  def revoke_uid(%Context{} = context, %Key{} = key, user_id, opts \\ [])
+      when is_binary(user_id) do
+    context.ref
+    |> ExGpgme.Native.context_revoke_uid(passphrase(opts), key.ref, user_id)
+    |> updated_key()
+  end
+
+  @spec set_primary_uid(t(), Key.t(), binary(), keyword()) :: {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Mark a user id of a key as the primary one. See `add_uid/4`.
+  """
+  def set_primary_uid(%Context{} = context, %Key{} = key, user_id, opts \\ [])
+      when is_binary(user_id) do
+    context.ref
+    |> ExGpgme.Native.context_set_primary_uid(passphrase(opts), key.ref, user_id)
+    |> updated_key()
+  end
+
+  @spec revoke_key(t(), Key.t(), keyword()) :: {:ok, binary(), Key.t()} | {:error, reason()}
+  @doc """
+  Revoke a key.
+
+  ## Options
+
+    * `:reason` - one of `:no_reason` (default), `:compromised`,
+      `:superseded` or `:no_longer_used`
+    * `:description` - text explaining the revocation
+    * `:passphrase` - passphrase unlocking the key
+
+  Returns the revocation certificate, a lone revocation signature that
+  revokes the key wherever it is imported, along with the updated key.
+  It follows the context's armor setting.
+  """
+  def revoke_key(%Context{} = context, %Key{} = key, opts \\ []) do
+    reason = Keyword.get(opts, :reason, :no_reason)
+    description = Keyword.get(opts, :description, "")
+
+    case ExGpgme.Native.context_revoke_key(
+           context.ref,
+           passphrase(opts),
+           key.ref,
+           reason,
+           description
+         ) do
+      {:ok, certificate, ref} -> {:ok, certificate, Key.from(ref)}
+      error -> error
+    end
+  end
+
+  @spec set_owner_trust(t(), Key.t(), Key.trust()) :: {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Set how far the owner of a key is trusted to certify other keys, one of
+  `:undefined`, `:never`, `:marginal`, `:full` or `:ultimate`. Keys
+  certified by trusted owners become valid recipients without
+  `:always_trust`. `:unknown`, no owner trust assigned at all, cannot be
+  set again and fails with `:invalid_value`.
+
+  Returns the updated key, see `ExGpgme.Key.owner_trust/1`.
+  """
+  def set_owner_trust(%Context{} = context, %Key{} = key, trust) when is_atom(trust) do
+    context.ref
+    |> ExGpgme.Native.context_set_owner_trust(key.ref, trust)
+    |> updated_key()
+  end
+
+  @spec set_tofu_policy(t(), Key.t(), :auto | :good | :unknown | :bad | :ask) ::
+          {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Set the TOFU policy of a key, e.g. `:bad` to mark a compromised
+  identity. Only has an effect when GnuPG runs with a TOFU trust model.
+
+  Returns the updated key, whose user ids carry an `ExGpgme.TofuInfo`.
+  """
+  def set_tofu_policy(%Context{} = context, %Key{} = key, policy) when is_atom(policy) do
+    context.ref
+    |> ExGpgme.Native.context_set_tofu_policy(key.ref, policy)
+    |> updated_key()
+  end
+
+  @spec locate_key(t(), binary(), keyword()) :: {:ok, Key.t()} | {:error, reason()}
+  @doc """
+  Find the key for an email address, fetching it from the network if it
+  is not in the keyring. Fetched keys are imported.
+
+  ## Options
+
+    * `:mechanisms` - where to look, in order: any of `:local`, `:wkd`,
+      `:keyserver`, `:dane`, `:cert`, `:pka`, `:ldap` and `:ntds`, or
+      keyserver URLs such as `"hkps://keys.openpgp.org"`. The keyring is
+      only checked if `:local` is in the list. Defaults to gpg's
+      `auto-key-locate` configuration, which checks the keyring first.
+
+  The key's `origin`, e.g. `:wkd` or `:key_server`, tells where it was
+  found.
+  """
+  def locate_key(%Context{} = context, email, opts \\ []) when is_binary(email) do
+    mechanisms =
+      case Keyword.get(opts, :mechanisms) do
+        nil -> ""
+        mechanisms -> Enum.map_join([:clear, :nodefault | mechanisms], ",", &to_string/1)
+      end
+
+    case ExGpgme.Native.key_locate(context.ref, email, mechanisms) do
+      {:ok, ref} -> {:ok, Key.from(ref)}
+      error -> error
+    end
+  end
+
+  @spec signature_notations(t()) :: {:ok, list()} | {:error, reason()}
   @doc """
   Return a list of signers.
   """
@@ -113,7 +914,7 @@ defmodule ExGpgme.Context do
   end
 
   @spec add_signature_notation(t(), binary(), binary(), keyword()) ::
-          {:ok, list()} | {:error, atom()}
+          {:ok, list()} | {:error, reason()}
   @doc """
   Return a list of add_signers.
   """
@@ -121,7 +922,7 @@ defmodule ExGpgme.Context do
     ExGpgme.Native.context_add_signature_notation(context.ref, name, value, flags)
   end
 
-  @spec clear_signature_notations(t()) :: {:ok, list()} | {:error, atom()}
+  @spec clear_signature_notations(t()) :: {:ok, list()} | {:error, reason()}
   @doc """
   Return a list of clear_signers.
   """
@@ -129,18 +930,18 @@ defmodule ExGpgme.Context do
     ExGpgme.Native.context_clear_signature_notations(context.ref)
   end
 
-  @spec sender(t()) :: {:ok, :none} | {:error, atom()}
+  @spec sender(t()) :: {:ok, :none} | {:ok, binary()} | {:error, reason()}
   @doc """
   Return currently active sender
   """
   def sender(%Context{} = context) do
     case ExGpgme.Native.context_sender(context.ref) do
-      :error -> {:ok, :none}
+      {:error, _} = error -> error
       sender -> {:ok, sender}
     end
   end
 
-  @spec clear_sender(t()) :: {:ok, :none} | {:error, atom()}
+  @spec clear_sender(t()) :: {:ok, :none} | {:error, reason()}
   @doc """
   Return currently active clear_sender
   """
@@ -148,29 +949,29 @@ defmodule ExGpgme.Context do
     ExGpgme.Native.context_clear_sender(context.ref)
   end
 
-  @spec set_sender(t(), binary()) :: {:ok, :none} | {:ok, binary()} | {:error, :not_found}
+  @spec set_sender(t(), binary()) :: {:ok, binary()} | {:error, reason()}
   @doc """
   Return currently active set_sender
   """
   def set_sender(%Context{} = context, sender) do
     case ExGpgme.Native.context_set_sender(context.ref, sender) do
-      :error -> {:error, :not_found}
       :ok -> {:ok, sender}
+      error -> error
     end
   end
 
-  @spec signers(t()) :: {:ok, :none} | {:error, atom()}
+  @spec signers(t()) :: {:ok, [Key.t()]} | {:error, reason()}
   @doc """
   Return currently active signers
   """
   def signers(%Context{} = context) do
     case ExGpgme.Native.context_signers(context.ref) do
-      :error -> {:ok, :none}
       {:ok, signers} -> {:ok, Enum.map(signers, &Key.from/1)}
+      error -> error
     end
   end
 
-  @spec clear_signers(t()) :: {:ok, :none} | {:error, atom()}
+  @spec clear_signers(t()) :: {:ok, :none} | {:error, reason()}
   @doc """
   Return currently active clear_signers
   """
@@ -178,14 +979,213 @@ defmodule ExGpgme.Context do
     ExGpgme.Native.context_clear_signers(context.ref)
   end
 
-  @spec add_signer(t(), Key.t()) :: {:ok, Key.t()} | {:error, :not_found}
+  @spec add_signer(t(), Key.t()) :: {:ok, Key.t()} | {:error, reason()}
   @doc """
   Return currently active add_signer
   """
   def add_signer(%Context{} = context, %Key{} = signer) do
     case ExGpgme.Native.context_add_signer(context.ref, signer.ref) do
-      :error -> {:error, :not_found}
       :ok -> {:ok, signer}
+      error -> error
+    end
+  end
+
+  @async_operations [
+    :list_keys,
+    :import_key,
+    :import_pkcs12,
+    :export_pkcs12,
+    :find_key,
+    :certificate_chain,
+    :locate_key,
+    :generate_key,
+    :add_subkey,
+    :export_keys,
+    :export_all,
+    :delete_key,
+    :sign_key,
+    :revoke_signature,
+    :set_expire,
+    :add_uid,
+    :revoke_uid,
+    :set_primary_uid,
+    :revoke_key,
+    :set_owner_trust,
+    :set_tofu_policy,
+    :encrypt,
+    :encrypt_symmetric,
+    :decrypt,
+    :encrypt_sign,
+    :decrypt_verify,
+    :sign,
+    :verify,
+    :encrypt_file,
+    :decrypt_file,
+    :sign_file,
+    :verify_file
+  ]
+
+  @spec async(atom(), list()) :: reference()
+  @doc """
+  Run one of the long running operations of this module in a separate
+  process. Once it finishes `{:gpgme_result, ref, result}` is sent to the
+  caller, where `result` is whatever the synchronous function returns.
+  Should the operation raise, exit or throw, `result` is
+  `{:error, %{code: :general, source: :ex_gpgme, message: message}}`.
+
+  ## Example
+
+      ref = ExGpgme.Context.async(:encrypt, [context, key, data, [:always_trust]])
+
+      receive do
+        {:gpgme_result, ^ref, {:ok, cipher_text}} -> cipher_text
+      end
+
+  """
+  def async(operation, args) when operation in @async_operations and is_list(args) do
+    caller = self()
+    ref = make_ref()
+
+    spawn(fn ->
+      result =
+        try do
+          apply(Context, operation, args)
+        catch
+          kind, reason ->
+            message = Exception.format_banner(kind, reason)
+            {:error, %{code: :general, source: :ex_gpgme, message: message}}
+        end
+
+      send(caller, {:gpgme_result, ref, result})
+    end)
+
+    ref
+  end
+
+  @stream_chunk_size 65_536
+
+  defp run_stream(source, start) do
+    source
+    |> stream_source()
+    |> Stream.map(&{:data, &1})
+    |> Stream.concat([:eof])
+    |> Stream.transform(
+      fn -> stream_result(start.()) end,
+      fn
+        _, {:done, _stream} = done ->
+          {:halt, done}
+
+        {:data, chunk}, stream ->
+          stream |> ExGpgme.Native.stream_write(chunk) |> stream_output(stream)
+
+        :eof, stream ->
+          {finish_stream(stream), {:done, stream}}
+      end,
+      fn
+        {:done, stream} -> ExGpgme.Native.stream_close(stream)
+        stream -> ExGpgme.Native.stream_close(stream)
+      end
+    )
+  end
+
+  # The worker may stop before all input is written, in which case the
+  # write already returns the remaining output like `stream_finish`.
+  defp stream_output({:ok, chunks}, stream), do: {chunks, stream}
+  defp stream_output({:done, chunks}, stream), do: {chunks, {:done, stream}}
+  defp stream_output({:error, reason}, _stream), do: raise(ExGpgme.Error, reason)
+
+  # Fetch the remaining output in batches, as it is consumed.
+  defp finish_stream(stream) do
+    :more
+    |> Stream.unfold(fn
+      :more -> stream |> ExGpgme.Native.stream_finish() |> stream_output(:more)
+      {:done, :more} -> nil
+    end)
+    |> Stream.concat()
+  end
+
+  defp stream_source(path) when is_binary(path), do: File.stream!(path, [], @stream_chunk_size)
+  defp stream_source(enumerable), do: enumerable
+
+  defp stream_result({:ok, result}), do: result
+  defp stream_result({:error, reason}), do: raise(ExGpgme.Error, reason)
+
+  defp fingerprints(recipients) when is_list(recipients) do
+    Enum.map(recipients, fn
+      %Key{fingerprint: fingerprint} -> fingerprint
+      fingerprint when is_binary(fingerprint) -> fingerprint
+    end)
+  end
+
+  defp fingerprints(recipient), do: fingerprints([recipient])
+
+  defp create_key_options(opts) do
+    {
+      Keyword.get(opts, :algorithm, "default"),
+      Keyword.get(opts, :expires, 0),
+      Keyword.get(opts, :flags, []),
+      passphrase(opts)
+    }
+  end
+
+  defp created_key({:ok, fingerprint, ref}), do: {:ok, fingerprint, Key.from(ref)}
+  defp created_key(error), do: error
+
+  defp updated_key({:ok, ref}), do: {:ok, Key.from(ref)}
+  defp updated_key(error), do: error
+
+  defp subkey_fingerprint(%{fingerprint: fingerprint}), do: fingerprint
+  defp subkey_fingerprint(fingerprint) when is_binary(fingerprint), do: fingerprint
+
+  @validity_levels [:marginal, :full, :ultimate]
+
+  defp require_validity(_signatures, nil), do: :ok
+
+  defp require_validity(signatures, required) when required in @validity_levels do
+    accepted = Enum.drop_while(@validity_levels, &(&1 != required))
+
+    trusted? = fn signature ->
+      signature.status == :ok and signature.validity in accepted
     end
+
+    if signatures != [] and Enum.all?(signatures, trusted?) do
+      :ok
+    else
+      message = "no good signature of #{required} validity"
+      {:error, %{code: :untrusted_signature, source: :ex_gpgme, message: message}}
+    end
+  end
+
+  defp require_validity(_signatures, _required) do
+    {:error, %{code: :invalid_value, source: :user, message: "unsupported validity"}}
+  end
+
+  defp default_home do
+    System.get_env("GNUPGHOME") || Path.join(System.user_home!(), ".gnupg")
+  end
+
+  defp from_ref(ref) do
+    with {:ok, info} <- ExGpgme.Native.context_info(ref),
+         {:ok, options} <- ExGpgme.Native.context_options(ref) do
+      context = %Context{
+        home: info.home,
+        path: info.path,
+        version: info.version,
+        protocol: info.protocol,
+        ref: ref
+      }
+
+      {:ok, struct(context, options)}
+    end
+  end
+
+  defp passphrase(opts), do: Keyword.get(opts, :passphrase, :provider)
+
+  defp user_id_list(:all), do: []
+  defp user_id_list(user_ids) when is_list(user_ids), do: user_ids
+
+  defp flag_options(opts) do
+    {flags, options} = Enum.split_with(opts, &is_atom/1)
+    {flags, passphrase(options)}
   end
 end
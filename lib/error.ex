@@ -0,0 +1,12 @@
+defmodule ExGpgme.Error do
+  @moduledoc """
+  Raised where an error tuple cannot be returned, e.g. while consuming
+  the streams built by `ExGpgme.Context.encrypt_stream/4`. Carries the
+  same `code`, `source` and `message` as `t:ExGpgme.Context.reason/0`.
+
+  Failures of the bindings themselves, e.g. a context left unusable by
+  an earlier crash, are raised with `source: :ex_gpgme`.
+  """
+
+  defexception [:code, :source, :message]
+end
@@ -5,27 +5,53 @@ defmodule ExGpgme do
 
   alias ExGpgme.Context
 
-  defdelegate create(opts), to: Context
-  defdelegate list_keys(context), to: Context
+  defdelegate create(opts \\ []), to: Context
+  defdelegate options(context), to: Context
+  defdelegate set_option(context, option, value), to: Context
+  defdelegate set_armor(context, armor), to: Context
+  defdelegate set_passphrase_provider(context, provider, opts \\ []), to: Context
+  defdelegate clear_passphrase_provider(context), to: Context
+  defdelegate list_keys(context, opts \\ []), to: Context
+  defdelegate stream_keys(context, opts \\ []), to: Context
   defdelegate import_key(context, data), to: Context
+  defdelegate import_pkcs12(context, data, passphrase), to: Context
+  defdelegate export_pkcs12(context, key, passphrase), to: Context
 
-  # TODO:
-  # defdelegate export_key(context, key), to: Context
-  # defdelegate create_key(context, key), to: Context
+  defdelegate generate_key(context, user_id, opts \\ []), to: Context
+  defdelegate add_subkey(context, key, algorithm, opts \\ []), to: Context
+  defdelegate export_keys(context, keys, mode \\ []), to: Context
+  defdelegate export_all(context, mode \\ []), to: Context
+  defdelegate delete_key(context, key, opts \\ []), to: Context
+  defdelegate sign_key(context, key, user_ids \\ :all, opts \\ []), to: Context
+  defdelegate revoke_signature(context, key, signer, opts \\ []), to: Context
+  defdelegate set_expire(context, key, expires, opts \\ []), to: Context
+  defdelegate add_uid(context, key, user_id, opts \\ []), to: Context
+  defdelegate revoke_uid(context, key, user_id, opts \\ []), to: Context
+  defdelegate set_primary_uid(context, key, user_id, opts \\ []), to: Context
+  defdelegate revoke_key(context, key, opts \\ []), to: Context
+  defdelegate set_owner_trust(context, key, trust), to: Context
+  defdelegate set_tofu_policy(context, key, policy), to: Context
 
-  defdelegate encrypt(context, key, data), to: Context
+  defdelegate encrypt(context, recipients, data, opts \\ []), to: Context
+  defdelegate encrypt_stream(context, recipients, source, opts \\ []), to: Context
   defdelegate encrypt_symmetric(context, passphrase, data), to: Context
   defdelegate decrypt(context, passphrase, data), to: Context
+  defdelegate decrypt_stream(context, passphrase, source), to: Context
 
-  # TODO:
-  # defdelegate encrypt_and_sign(context, key, data), to: Context
-  # defdelegate decrypt_and_verify(context, passphrase, data), to: Context
+  defdelegate encrypt_file(context, recipients, source, destination, opts \\ []), to: Context
+  defdelegate decrypt_file(context, passphrase, source, destination), to: Context
+  defdelegate sign_file(context, source, destination, opts \\ []), to: Context
+  defdelegate verify_file(context, signature, destination, opts \\ []), to: Context
+
+  defdelegate encrypt_sign(context, recipients, data, opts \\ []), to: Context
+  defdelegate decrypt_verify(context, passphrase, data), to: Context
 
   defdelegate find_key(context, query), to: Context
+  defdelegate certificate_chain(context, key), to: Context
+  defdelegate locate_key(context, email, opts \\ []), to: Context
 
-  # TODO:
-  # defdelegate sign(context, mode = :normal|:clear|:detached, key, data), to: Context
-  # defdelegate verify(context, mode = :normal|:detached|:opaque, passphrase, data), to: Context
+  defdelegate sign(context, data, opts \\ []), to: Context
+  defdelegate verify(context, signature, opts \\ []), to: Context
 
   defdelegate signature_notations(context), to: Context
   defdelegate add_signature_notation(context, name, value, flags), to: Context
@@ -35,6 +61,8 @@ defmodule ExGpgme do
   defdelegate set_sender(context, sender), to: Context
   defdelegate clear_sender(context), to: Context
 
+  defdelegate async(operation, args), to: Context
+
   defdelegate signers(context), to: Context
   defdelegate add_signer(context, signer), to: Context
   defdelegate clear_signers(context), to: Context
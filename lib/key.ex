@@ -1,11 +1,20 @@
 defmodule ExGpgme.Key do
   @moduledoc """
   Documentation for Key.
+
+  Keys of `:cms` contexts are X.509 certificates. For those
+  `issuer_serial` and `issuer_name` identify the certificate within its
+  issuer, `chain_id` is the fingerprint of the issuer's certificate (or
+  its own for a root certificate) and `card_key` and
+  `card_serial_number` tell whether the secret key lives on a smartcard.
+  See `ExGpgme.Context.certificate_chain/2` to walk up to the root.
   """
 
   alias __MODULE__
+  alias ExGpgme.{Subkey, UserId}
 
-  @type t :: Key
+  @type t :: %Key{user_ids: [UserId.t()], subkeys: [Subkey.t()]}
+  @type trust :: :unknown | :undefined | :never | :marginal | :full | :ultimate
 
   defstruct id: nil,
             ref: nil,
@@ -20,6 +29,15 @@ defmodule ExGpgme.Key do
             is_disabled: false,
             is_invalid: false,
             is_qualified: false,
+            owner_trust: :unknown,
+            origin: :unknown,
+            last_update: :unknown,
+            protocol: nil,
+            issuer_serial: :none,
+            issuer_name: :none,
+            chain_id: :none,
+            card_key: false,
+            card_serial_number: :none,
             user_ids: [],
             subkeys: []
 
@@ -27,37 +45,27 @@ defmodule ExGpgme.Key do
   Get a key map.
   """
   def from(ref) when is_reference(ref) do
-    {:ok, id} = ExGpgme.Native.key_id(ref)
-    {:ok, fingerprint} = ExGpgme.Native.key_fingerprint(ref)
-    {:ok, has_secret} = ExGpgme.Native.key_has_secret(ref)
-    {:ok, can_encrypt} = ExGpgme.Native.key_can_encrypt(ref)
-    {:ok, can_sign} = ExGpgme.Native.key_can_sign(ref)
-    {:ok, can_certify} = ExGpgme.Native.key_can_certify(ref)
-    {:ok, can_authenticate} = ExGpgme.Native.key_can_authenticate(ref)
-    {:ok, is_revoked} = ExGpgme.Native.key_is_revoked(ref)
-    {:ok, is_expired} = ExGpgme.Native.key_is_expired(ref)
-    {:ok, is_disabled} = ExGpgme.Native.key_is_disabled(ref)
-    {:ok, is_invalid} = ExGpgme.Native.key_is_invalid(ref)
-    {:ok, is_qualified} = ExGpgme.Native.key_is_qualified(ref)
-    {:ok, user_ids} = ExGpgme.Native.key_user_ids(ref)
-    {:ok, subkeys} = ExGpgme.Native.key_subkeys(ref)
+    {:ok, map} = ExGpgme.Native.key_to_map(ref)
+    key = struct(Key, map)
 
     %Key{
-      id: id,
-      ref: ref,
-      fingerprint: fingerprint,
-      has_secret: has_secret,
-      can_encrypt: can_encrypt,
-      can_sign: can_sign,
-      can_certify: can_certify,
-      can_authenticate: can_authenticate,
-      is_revoked: is_revoked,
-      is_expired: is_expired,
-      is_disabled: is_disabled,
-      is_invalid: is_invalid,
-      is_qualified: is_qualified,
-      user_ids: user_ids,
-      subkeys: subkeys
+      key
+      | ref: ref,
+        user_ids: Enum.map(key.user_ids, &UserId.from_map(&1, key.protocol)),
+        subkeys: Enum.map(key.subkeys, &Subkey.from_map/1)
     }
   end
+
+  @spec owner_trust(t()) :: trust()
+  @doc """
+  Return how far the owner of the key is trusted to certify other keys,
+  as recorded in the trust database. Keys that were never assigned an
+  owner trust are `:unknown`. gpgme reports an owner trust set to
+  `:undefined` as `:unknown` as well. See
+  `ExGpgme.Context.set_owner_trust/3`.
+  """
+  def owner_trust(%Key{ref: ref}) do
+    {:ok, owner_trust} = ExGpgme.Native.key_owner_trust(ref)
+    owner_trust
+  end
 end
@@ -0,0 +1,24 @@
+defmodule ExGpgme.KeySignature do
+  @moduledoc """
+  A certification on a user id of an `ExGpgme.Key`. Only listed if the
+  key was fetched with the `:sigs` key listing mode, see
+  `ExGpgme.Context.list_keys/2`.
+  """
+
+  alias __MODULE__
+
+  @type t :: %KeySignature{}
+
+  defstruct signer_key: nil,
+            signer: :none,
+            algorithm: nil,
+            creation_time: :unknown,
+            expiration_time: :unknown,
+            invalid: false,
+            revoked: false,
+            exportable: true,
+            status: nil
+
+  @doc false
+  def from_map(map) when is_map(map), do: struct(KeySignature, map)
+end
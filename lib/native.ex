@@ -12,6 +12,21 @@ defmodule ExGpgme.Native do
   def key_list(_ctx),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def key_list(_ctx, _patterns, _secret_only, _mode),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def key_locate(_ctx, _email, _mechanisms),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def key_stream(_ctx, _patterns, _secret_only, _mode, _batch_size),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def key_stream_next(_stream, _count),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def key_stream_close(_stream),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def key_id(_key_ref),
     do: :erlang.nif_error(:nif_not_loaded)
 
@@ -54,17 +69,50 @@ defmodule ExGpgme.Native do
   def key_is_qualified(_key_ref),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def key_owner_trust(_key_ref),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def key_to_map(_key_ref),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   # context related
   def context_create(_protocol, _path),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def context_create(_protocol, _home, _options),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_options(_ctx),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_set_option(_ctx, _option, _value),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_set_passphrase_provider(_ctx, _pid, _timeout),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_clear_passphrase_provider(_ctx),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def passphrase_reply(_reply, _answer),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def context_info(_ctx),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def context_armor(_ctx),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_set_armor(_ctx, _armor),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def context_import(_ctx, _data),
     do: :erlang.nif_error(:nif_not_loaded)
 
-  def context_encrypt(_ctx, _key, _data),
+  def context_import_pkcs12(_ctx, _passphrase, _data),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_encrypt(_ctx, _passphrase, _recipients, _data, _flags),
     do: :erlang.nif_error(:nif_not_loaded)
 
   def context_encrypt_symmetric(_ctx, _passphrase, _data),
@@ -73,9 +121,75 @@ defmodule ExGpgme.Native do
   def context_decrypt(_ctx, _key, _data),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def context_sign(_ctx, _passphrase, _mode, _data),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_verify(_ctx, _signature),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_verify_detached(_ctx, _signature, _signed_text),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_encrypt_sign(_ctx, _passphrase, _recipients, _data, _flags),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_decrypt_verify(_ctx, _passphrase, _data),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_encrypt_file(_ctx, _passphrase, _recipients, _source, _destination, _flags),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_decrypt_file(_ctx, _passphrase, _source, _destination),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_sign_file(_ctx, _passphrase, _mode, _source, _destination),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_verify_file(_ctx, _signature, _signed, _destination),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def context_find_key(_ctx, _query),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  def context_generate_key(_ctx, _passphrase, _user_id, _algorithm, _expires, _flags),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_add_subkey(_ctx, _passphrase, _key_ref, _algorithm, _expires, _flags),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_export(_ctx, _passphrase, _patterns, _mode),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_delete_key(_ctx, _fingerprint, _flags),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_sign_key(_ctx, _passphrase, _key_ref, _user_ids, _expires, _flags),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_revoke_signature(_ctx, _passphrase, _key_ref, _signer_ref, _user_ids),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_set_expire(_ctx, _passphrase, _key_ref, _expires, _subkeys),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_add_uid(_ctx, _passphrase, _key_ref, _user_id),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_revoke_uid(_ctx, _passphrase, _key_ref, _user_id),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_set_primary_uid(_ctx, _passphrase, _key_ref, _user_id),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_revoke_key(_ctx, _passphrase, _key_ref, _reason, _description),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_set_owner_trust(_ctx, _key_ref, _trust),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def context_set_tofu_policy(_ctx, _key_ref, _policy),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   def context_signature_notations(_ctx),
     do: :erlang.nif_error(:nif_not_loaded)
 
@@ -102,4 +216,20 @@ defmodule ExGpgme.Native do
 
   def context_clear_signers(_ctx),
     do: :erlang.nif_error(:nif_not_loaded)
+
+  # streaming
+  def stream_encrypt(_ctx, _passphrase, _recipients, _flags),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def stream_decrypt(_ctx, _passphrase),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def stream_write(_stream, _chunk),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def stream_finish(_stream),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  def stream_close(_stream),
+    do: :erlang.nif_error(:nif_not_loaded)
 end
@@ -0,0 +1,95 @@
+defmodule ExGpgme.PassphraseProvider do
+  @moduledoc """
+  Answer gpgme's passphrase requests from Elixir instead of passing a
+  fixed passphrase to every call.
+
+  Once a provider is registered with
+  `ExGpgme.Context.set_passphrase_provider/3`, every operation on that
+  context that is given no passphrase (or `:provider`) asks the provider
+  instead. Each request arrives as a message
+
+      {:gpgme_passphrase_request, reply, request}
+
+  and has to be answered with `reply/2` before the timeout passes, or the
+  operation fails with a `:timeout` error. A wrong passphrase is asked
+  for again with `prev_was_bad: true`, so a provider can e.g. prompt the
+  user a second time or give up with `:cancel`.
+
+  Any process can serve requests, but implementing this behaviour and
+  starting it with `start_link/3` takes care of the message handling.
+
+  ## Example
+
+      defmodule Vault do
+        @behaviour ExGpgme.PassphraseProvider
+
+        def init(passphrases), do: {:ok, passphrases}
+
+        def handle_passphrase_request(%{prev_was_bad: true}, state),
+          do: {:cancel, state}
+
+        def handle_passphrase_request(%{uid_hint: hint}, passphrases),
+          do: {:ok, Map.fetch!(passphrases, hint), passphrases}
+      end
+
+      {:ok, provider} = ExGpgme.PassphraseProvider.start_link(Vault, passphrases)
+      :ok = ExGpgme.Context.set_passphrase_provider(context, provider)
+
+  """
+
+  use GenServer
+
+  @typedoc """
+  `uid_hint` is the key id followed by the primary user id of the key
+  that is to be unlocked, `description` is gpg-agent's description of
+  the request. Both are `:none` for symmetric passphrases.
+  """
+  @type request :: %{
+          uid_hint: binary() | :none,
+          description: binary() | :none,
+          prev_was_bad: boolean()
+        }
+  @type answer :: {:ok, binary()} | :cancel
+
+  @callback init(arg :: term()) :: {:ok, state :: term()}
+  @callback handle_passphrase_request(request(), state :: term()) ::
+              {:ok, passphrase :: binary(), state :: term()} | {:cancel, state :: term()}
+
+  @spec start_link(module(), term(), GenServer.options()) :: GenServer.on_start()
+  @doc """
+  Start a process serving passphrase requests with the callbacks of
+  `module`.
+  """
+  def start_link(module, arg, opts \\ []) do
+    GenServer.start_link(__MODULE__, {module, arg}, opts)
+  end
+
+  @spec reply(reference(), answer()) :: :ok
+  @doc """
+  Answer a passphrase request with `{:ok, passphrase}`, or `:cancel` to
+  abort the operation.
+  """
+  def reply(reply, {:ok, passphrase} = answer) when is_binary(passphrase),
+    do: ExGpgme.Native.passphrase_reply(reply, answer)
+
+  def reply(reply, :cancel), do: ExGpgme.Native.passphrase_reply(reply, :cancel)
+
+  @impl true
+  def init({module, arg}) do
+    {:ok, state} = module.init(arg)
+    {:ok, {module, state}}
+  end
+
+  @impl true
+  def handle_info({:gpgme_passphrase_request, reply, request}, {module, state}) do
+    case module.handle_passphrase_request(request, state) do
+      {:ok, passphrase, state} ->
+        reply(reply, {:ok, passphrase})
+        {:noreply, {module, state}}
+
+      {:cancel, state} ->
+        reply(reply, :cancel)
+        {:noreply, {module, state}}
+    end
+  end
+end
@@ -0,0 +1,31 @@
+defmodule ExGpgme.Subkey do
+  @moduledoc """
+  A subkey of an `ExGpgme.Key`. The first subkey is the primary key.
+  Times are Unix timestamps, or `:unknown` if not set.
+  """
+
+  alias __MODULE__
+
+  @type t :: %Subkey{}
+
+  defstruct fingerprint: nil,
+            secret: false,
+            algorithm: nil,
+            expired: false,
+            creation_time: :unknown,
+            expiration_time: :unknown,
+            curve: :unknown,
+            length: 0,
+            card_key: false,
+            card_serial_number: :none,
+            revoked: false,
+            invalid: false,
+            disabled: false,
+            can_sign: false,
+            can_encrypt: false,
+            can_certify: false,
+            can_auth: false
+
+  @doc false
+  def from_map(map) when is_map(map), do: struct(Subkey, map)
+end
@@ -0,0 +1,30 @@
+defmodule ExGpgme.TofuInfo do
+  @moduledoc """
+  Trust-on-first-use statistics for an `ExGpgme.UserId`. Only available
+  when GnuPG runs with a TOFU trust model and the key was listed with
+  the `:with_tofu` key listing mode.
+
+  `validity` is one of `:conflict`, `:no_history`, `:little_history`,
+  `:basic_history` or `:large_history`. Timestamps are Unix timestamps,
+  or `:none` if the key was never seen in that role.
+  """
+
+  alias __MODULE__
+
+  @type policy :: :none | :auto | :good | :unknown | :bad | :ask
+  @type t :: %TofuInfo{}
+
+  defstruct validity: nil,
+            policy: :none,
+            signature_count: 0,
+            encrypted_count: 0,
+            signature_first_seen: :none,
+            signature_last_seen: :none,
+            encrypted_first_seen: :none,
+            encrypted_last_seen: :none,
+            description: :none
+
+  @doc false
+  def from_map(:none), do: :none
+  def from_map(map) when is_map(map), do: struct(TofuInfo, map)
+end
@@ -0,0 +1,118 @@
+defmodule ExGpgme.UserId do
+  @moduledoc """
+  A user id of an `ExGpgme.Key`. `uid` is the full user id, e.g.
+  `"Foo McBar <foo@mcbar.dev>"`, as expected by functions such as
+  `ExGpgme.Context.sign_key/4`.
+
+  The first user id of an X.509 certificate is its subject, a
+  distinguished name such as `"CN=Foo McBar,O=ExGpgme Test,C=DE"`. Its
+  components are available in order as `dn`, e.g.
+  `[{"CN", "Foo McBar"}, {"O", "ExGpgme Test"}, {"C", "DE"}]`. `dn` is
+  `:none` for every other user id.
+  """
+
+  alias __MODULE__
+  alias ExGpgme.{KeySignature, TofuInfo}
+
+  @type t :: %UserId{}
+  @type dn :: [{attribute :: binary(), value :: binary()}]
+
+  defstruct uid: nil,
+            name: nil,
+            email: nil,
+            comment: nil,
+            dn: :none,
+            validity: :unknown,
+            revoked: false,
+            invalid: false,
+            origin: :unknown,
+            tofu_info: :none,
+            signatures: []
+
+  # Attributes gpgsm reports by their OID only.
+  @attributes %{"1.2.840.113549.1.9.1" => "EMail"}
+
+  @doc false
+  def from_map(map, protocol \\ :openpgp) when is_map(map) do
+    user_id = struct(UserId, map)
+
+    %UserId{
+      user_id
+      | dn: dn(protocol, user_id.uid),
+        tofu_info: TofuInfo.from_map(user_id.tofu_info),
+        signatures: Enum.map(user_id.signatures, &KeySignature.from_map/1)
+    }
+  end
+
+  # Further user ids of certificates hold alternative names, e.g.
+  # `"<foo@mcbar.dev>"`.
+  defp dn(:cms, "<" <> _), do: :none
+  defp dn(:cms, "(" <> _), do: :none
+
+  defp dn(:cms, uid) when is_binary(uid) do
+    uid
+    |> split_dn()
+    |> Enum.reduce_while([], fn component, acc ->
+      case String.split(component, "=", parts: 2) do
+        [attribute, value] -> {:cont, [{attribute(attribute), value(value)} | acc]}
+        _ -> {:halt, :none}
+      end
+    end)
+    |> case do
+      :none -> :none
+      components -> Enum.reverse(components)
+    end
+  end
+
+  defp dn(_protocol, _uid), do: :none
+
+  # Split at the commas and pluses that are neither escaped nor quoted,
+  # keeping escapes for `value/1`.
+  defp split_dn(string, current \\ "", quoted \\ false, acc \\ [])
+
+  defp split_dn("", current, _quoted, acc), do: Enum.reverse([current | acc])
+
+  defp split_dn(<<"\\", char::utf8, rest::binary>>, current, quoted, acc),
+    do: split_dn(rest, <<current::binary, "\\", char::utf8>>, quoted, acc)
+
+  defp split_dn(<<"\"", rest::binary>>, current, quoted, acc),
+    do: split_dn(rest, <<current::binary, "\"">>, not quoted, acc)
+
+  defp split_dn(<<sep, rest::binary>>, current, false, acc) when sep in [?,, ?+],
+    do: split_dn(rest, "", false, [current | acc])
+
+  defp split_dn(<<char::utf8, rest::binary>>, current, quoted, acc),
+    do: split_dn(rest, <<current::binary, char::utf8>>, quoted, acc)
+
+  defp attribute(attribute) do
+    attribute = String.trim(attribute)
+    Map.get(@attributes, attribute, attribute)
+  end
+
+  # Values are either hex encoded, `#666F6F`, or strings with escapes.
+  defp value("#" <> hex = value) do
+    case Base.decode16(hex, case: :mixed) do
+      {:ok, decoded} -> decoded
+      :error -> value
+    end
+  end
+
+  defp value(value), do: value |> String.trim() |> String.trim("\"") |> unescape("")
+
+  defp unescape("", acc), do: acc
+
+  defp unescape(<<"\\", hex::binary-size(2), rest::binary>> = escaped, acc) do
+    case Base.decode16(hex, case: :mixed) do
+      {:ok, byte} -> unescape(rest, acc <> byte)
+      :error -> unescape_char(escaped, acc)
+    end
+  end
+
+  defp unescape(<<"\\", _::binary>> = escaped, acc), do: unescape_char(escaped, acc)
+  defp unescape(<<char::utf8, rest::binary>>, acc), do: unescape(rest, <<acc::binary, char::utf8>>)
+
+  defp unescape_char(<<"\\", char::utf8, rest::binary>>, acc),
+    do: unescape(rest, <<acc::binary, char::utf8>>)
+
+  defp unescape_char(<<"\\">>, acc), do: acc <> "\\"
+end
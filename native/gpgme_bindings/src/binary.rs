@@ -0,0 +1,18 @@
+use error;
+use rustler::types::binary::OwnedBinary;
+use rustler::{Encoder, Env, NifResult, Term};
+
+/// Copy the bytes produced by gpgme into a new Erlang binary, without
+/// assuming anything about their encoding.
+pub fn to_term<'a>(env: Env<'a>, bytes: &[u8]) -> NifResult<Term<'a>> {
+    match OwnedBinary::new(bytes.len()) {
+        Some(mut binary) => {
+            binary.as_mut_slice().copy_from_slice(bytes);
+            Ok(binary.release(env).encode(env))
+        }
+        None => Err(error::raise(
+            error::code::out_of_memory(),
+            "could not allocate a binary",
+        )),
+    }
+}
@@ -1,19 +1,25 @@
 use atoms;
-use gpgme::{Context, PinentryMode, Protocol, SignatureNotationFlags};
+use binary;
+use error;
+use gpgme::{Context, Protocol, SignatureNotationFlags};
 use key::GpgmeKey;
+use passphrase::{self, Passphrase, Provider};
 use rustler::resource::ResourceArc;
+use rustler::types::atom::Atom;
+use rustler::types::binary::Binary;
 use rustler::{Encoder, Env, NifResult, Term};
 use std::io::prelude::*;
 use std::sync::Mutex;
 
-pub(crate) struct GpgmeContext(pub Mutex<Context>);
+/// A context together with the passphrase provider registered on it.
+pub(crate) struct GpgmeContext(pub Mutex<Context>, pub Mutex<Option<Provider>>);
 
 unsafe impl Send for GpgmeContext {}
 unsafe impl Sync for GpgmeContext {}
 
 impl std::convert::From<Context> for GpgmeContext {
     fn from(ctx: Context) -> GpgmeContext {
-        GpgmeContext(Mutex::new(ctx))
+        GpgmeContext(Mutex::new(ctx), Mutex::new(None))
     }
 }
 
@@ -27,48 +33,122 @@ mod keys {
     }
 }
 
-fn create_wrapped(proto: Protocol, path: &str) -> Result<GpgmeContext, rustler::Error> {
-    match Context::from_protocol(proto) {
-        Ok(mut ctx) => {
-            ctx.set_armor(true);
-            ctx.set_text_mode(true);
-
-            ctx.set_pinentry_mode(PinentryMode::Loopback)
-                .expect("could not set pinentry mode");
-            ctx.set_engine_home_dir(path)
-                .expect("Fatal: could not set home dir");
-            Ok(ctx.into())
-        }
-        Err(_err) => Err(rustler::Error::Atom("context_initialization")),
+/// Open a second context with the same engine and settings, for long
+/// running work that should not hold the lock of the original.
+pub(crate) fn duplicate(context: &Context) -> Result<Context, gpgme::Error> {
+    let info = context.engine_info();
+    let mut ctx = Context::from_protocol(context.protocol())?;
+    if let Ok(path) = info.path() {
+        ctx.set_engine_path(path)?;
     }
+    if let Ok(home) = info.home_dir() {
+        ctx.set_engine_home_dir(home)?;
+    }
+    ctx.set_armor(context.armor());
+    ctx.set_text_mode(context.text_mode());
+    ctx.set_pinentry_mode(context.pinentry_mode())?;
+    ctx.set_offline(context.offline());
+    ctx.set_certificate_number(context.certificate_number());
+    ctx.set_key_list_mode(context.key_list_mode())?;
+    Ok(ctx)
 }
 
-/// Create a new Ggpme Context and return it to erlang as a reference.
+/// Run `f` on the context, answering every passphrase request with
+/// `passphrase`, see `passphrase::answer`.
+pub(crate) fn with_passphrase<R, F>(
+    res: &GpgmeContext,
+    context: &mut Context,
+    passphrase: &Passphrase,
+    f: F,
+) -> R
+where
+    F: FnOnce(&mut Context) -> R,
+{
+    context.with_passphrase_provider(
+        |req: gpgme::PassphraseRequest, out: &mut Write| {
+            let answer = passphrase::answer(res, passphrase, &req)?;
+            match out.write_all(&answer) {
+                Ok(()) => Ok(()),
+                Err(_) => Err(gpgme::Error::from_code(32)),
+            }
+        },
+        f,
+    )
+}
+
+/// Create a context with the same defaults as `ExGpgme.Context.create/1`.
 pub fn create<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
-    let proto: Protocol = crate::protocol::from_term(args[0])?;
-    let path: String = args[1].decode()?;
-    let context = create_wrapped(proto, &path)?;
-    let resource = ResourceArc::new(context);
-    Ok((crate::atoms::ok(), resource).encode(env))
+    let proto = match crate::protocol::from_term(args[0]) {
+        Ok(proto) => proto,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let home: String = args[1].decode()?;
+    create_context(env, proto, &home, options::defaults(env))
+}
+
+/// Create a context from a protocol, a home directory and a keyword
+/// list of options, see `set_option`. Unlike `create` nothing is set
+/// unless asked for.
+pub fn create_with_options<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let proto = match crate::protocol::from_term(args[0]) {
+        Ok(proto) => proto,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let home: String = args[1].decode()?;
+    let options: Vec<(Atom, Term<'a>)> = args[2].decode()?;
+    create_context(env, proto, &home, options)
+}
+
+fn create_context<'a>(
+    env: Env<'a>,
+    proto: Protocol,
+    home: &str,
+    options: Vec<(Atom, Term<'a>)>,
+) -> NifResult<Term<'a>> {
+    let mut context = match Context::from_protocol(proto)
+        .and_then(|mut ctx| ctx.set_engine_home_dir(home).map(|_| ctx))
+    {
+        Ok(context) => context,
+        Err(err) => return error::as_term(env, err),
+    };
+    for (name, value) in options {
+        match options::set(&mut context, name, value) {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => return error::as_term(env, err),
+            Err(err) => return error::invalid_argument(env, err),
+        }
+    }
+
+    let resource = ResourceArc::new(GpgmeContext::from(context));
+    Ok((atoms::ok(), resource).encode(env))
 }
 
 pub fn info<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let context = res.0.lock().unwrap();
+    let context = error::lock(&res.0)?;
     let info = context.engine_info();
 
     let mut map = Term::map_new(env);
 
     map = map.map_put(
         keys::home().encode(env),
-        info.home_dir().unwrap().encode(env),
+        info.home_dir()
+            .map(|s| s.encode(env))
+            .unwrap_or(atoms::none().encode(env)),
     )?;
 
-    map = map.map_put(keys::path().encode(env), info.path().unwrap().encode(env))?;
+    map = map.map_put(
+        keys::path().encode(env),
+        info.path()
+            .map(|s| s.encode(env))
+            .unwrap_or(atoms::none().encode(env)),
+    )?;
 
     map = map.map_put(
         keys::version().encode(env),
-        info.version().unwrap().encode(env),
+        info.version()
+            .map(|s| s.encode(env))
+            .unwrap_or(atoms::none().encode(env)),
     )?;
 
     map = map.map_put(
@@ -78,12 +158,202 @@ pub fn info<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
 
     map = map.map_put(
         keys::required_version().encode(env),
-        info.required_version().unwrap().encode(env),
+        info.required_version()
+            .map(|s| s.encode(env))
+            .unwrap_or(atoms::none().encode(env)),
     )?;
 
     Ok((crate::atoms::ok(), map).encode(env))
 }
 
+pub fn armor<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let context = error::lock(&res.0)?;
+    Ok((atoms::ok(), context.armor()).encode(env))
+}
+
+/// Toggle ASCII armored output. With armor disabled, encrypt and sign
+/// return binary OpenPGP packets.
+pub fn set_armor<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let armor: bool = args[1].decode()?;
+    let mut context = error::lock(&res.0)?;
+    context.set_armor(armor);
+    Ok(atoms::ok().encode(env))
+}
+
+pub(crate) mod pinentry_mode {
+    use error::{ArgError, ArgResult};
+    use gpgme::PinentryMode;
+    use rustler::types::atom::Atom;
+    use rustler::Term;
+
+    rustler_atoms! {
+        atom default;
+        atom ask;
+        atom cancel;
+        atom error;
+        atom loopback;
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<PinentryMode> {
+        let mode: Atom = term.decode()?;
+        match mode {
+            m if m == default() => Ok(PinentryMode::Default),
+            m if m == ask() => Ok(PinentryMode::Ask),
+            m if m == cancel() => Ok(PinentryMode::Cancel),
+            m if m == error() => Ok(PinentryMode::Error),
+            m if m == loopback() => Ok(PinentryMode::Loopback),
+            _ => Err(ArgError::Invalid("unsupported pinentry mode")),
+        }
+    }
+
+    pub fn as_term(mode: PinentryMode) -> Atom {
+        match mode {
+            PinentryMode::Ask => ask(),
+            PinentryMode::Cancel => cancel(),
+            PinentryMode::Error => error(),
+            PinentryMode::Loopback => loopback(),
+            _ => default(),
+        }
+    }
+}
+
+/// The settings of a context that can be chosen at creation and changed
+/// later on.
+mod options {
+    use super::pinentry_mode;
+    use atoms;
+    use error::{ArgError, ArgResult};
+    use gpgme::{Context, PinentryMode};
+    use key::key_list_mode;
+    use rustler::types::atom::Atom;
+    use rustler::{Encoder, Env, NifResult, Term};
+
+    rustler_atoms! {
+        atom armor;
+        atom text_mode;
+        atom pinentry_mode;
+        atom offline;
+        atom include_certs;
+        atom key_list_mode;
+        atom engine_path;
+        atom home;
+        atom default;
+    }
+
+    /// `:default` leaves the number of certificates to include in S/MIME
+    /// signatures up to the engine.
+    fn include_certs_from<'a>(term: Term<'a>) -> NifResult<Option<u32>> {
+        match term.decode::<Atom>() {
+            Ok(atom) if atom == default() => Ok(None),
+            _ => term.decode().map(Some),
+        }
+    }
+
+    /// The options `ExGpgme.Context.create/1` applies unless told
+    /// otherwise.
+    pub fn defaults<'a>(env: Env<'a>) -> Vec<(Atom, Term<'a>)> {
+        vec![
+            (armor(), true.encode(env)),
+            (text_mode(), true.encode(env)),
+            (
+                pinentry_mode(),
+                pinentry_mode::as_term(PinentryMode::Loopback).encode(env),
+            ),
+        ]
+    }
+
+    /// Apply a single option. Unknown options and values are reported as
+    /// `ArgError`s, settings the engine refuses as `gpgme::Error`s.
+    pub fn set<'a>(
+        context: &mut Context,
+        name: Atom,
+        value: Term<'a>,
+    ) -> ArgResult<Result<(), gpgme::Error>> {
+        let result = match name {
+            n if n == armor() => {
+                context.set_armor(value.decode()?);
+                Ok(())
+            }
+            n if n == text_mode() => {
+                context.set_text_mode(value.decode()?);
+                Ok(())
+            }
+            n if n == pinentry_mode() => {
+                context.set_pinentry_mode(pinentry_mode::from_term(value)?)
+            }
+            n if n == offline() => {
+                context.set_offline(value.decode()?);
+                Ok(())
+            }
+            n if n == include_certs() => {
+                context.set_certificate_number(include_certs_from(value)?);
+                Ok(())
+            }
+            n if n == key_list_mode() => {
+                context.set_key_list_mode(key_list_mode::from_term(value)?)
+            }
+            n if n == engine_path() => {
+                let path: String = value.decode()?;
+                context.set_engine_path(path.as_str())
+            }
+            n if n == home() => {
+                let home: String = value.decode()?;
+                context.set_engine_home_dir(home.as_str())
+            }
+            _ => return Err(ArgError::Invalid("unsupported context option")),
+        };
+        Ok(result)
+    }
+
+    pub fn as_map<'a>(env: Env<'a>, context: &Context) -> NifResult<Term<'a>> {
+        let include = match context.certificate_number() {
+            Some(n) => n.encode(env),
+            None => default().encode(env),
+        };
+        let fields = vec![
+            (armor(), context.armor().encode(env)),
+            (text_mode(), context.text_mode().encode(env)),
+            (
+                pinentry_mode(),
+                pinentry_mode::as_term(context.pinentry_mode()).encode(env),
+            ),
+            (offline(), context.offline().encode(env)),
+            (include_certs(), include),
+            (
+                key_list_mode(),
+                key_list_mode::as_list(context.key_list_mode()).encode(env),
+            ),
+        ];
+
+        let mut map = Term::map_new(env);
+        for (field, value) in fields {
+            map = map.map_put(field.encode(env), value)?;
+        }
+        Ok((atoms::ok(), map).encode(env))
+    }
+}
+
+/// The current settings of the context, see `options`.
+pub fn options<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let context = error::lock(&res.0)?;
+    options::as_map(env, &context)
+}
+
+/// Change one setting of an existing context, e.g. `:pinentry_mode`.
+pub fn set_option<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let name: Atom = args[1].decode()?;
+    let mut context = error::lock(&res.0)?;
+    match options::set(&mut context, name, args[2]) {
+        Ok(Ok(())) => Ok(atoms::ok().encode(env)),
+        Ok(Err(err)) => error::as_term(env, err),
+        Err(err) => error::invalid_argument(env, err),
+    }
+}
+
 mod import_result {
     use atoms;
     use gpgme::ImportResult;
@@ -91,6 +361,7 @@ mod import_result {
 
     mod imports {
         use atoms;
+        use error;
         use gpgme::Import;
         use rustler::{Encoder, Env, NifResult, Term};
 
@@ -112,7 +383,7 @@ mod import_result {
 
             let import_result = match import.result() {
                 Ok(_) => atoms::ok(),
-                _ => atoms::error(),
+                Err(err) => error::code::from(err),
             };
 
             let import_status = format!("{:?}", import.status());
@@ -198,137 +469,763 @@ mod import_result {
 
 pub fn import<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let data: String = args[1].decode()?;
-    let mut context = res.0.lock().unwrap();
-    match context.import(data) {
+    let data: Binary = args[1].decode()?;
+    let mut context = error::lock(&res.0)?;
+    match context.import(data.as_slice()) {
+        Ok(result) => import_result::from(env, result),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Import a PKCS#12 bundle into a CMS context. The passphrase protecting
+/// the bundle is asked for like any other, and also protects the imported
+/// secret key.
+pub fn import_pkcs12<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let data: Binary = args[2].decode()?;
+    let mut context = error::lock(&res.0)?;
+    let result = with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.import(data.as_slice())
+    });
+    match result {
         Ok(result) => import_result::from(env, result),
-        Err(_err) => Err(rustler::Error::Atom("error")),
+        Err(err) => error::as_term(env, err),
     }
 }
 
+pub(crate) mod encrypt_flags {
+    use error::{ArgError, ArgResult};
+    use gpgme::EncryptFlags;
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom always_trust;
+        atom no_encrypt_to;
+        atom prepare;
+        atom expect_sign;
+        atom no_compress;
+        atom symmetric;
+        atom throw_keyids;
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<EncryptFlags> {
+        let flags: Vec<types::atom::Atom> = term.decode()?;
+        flags.iter().fold(Ok(EncryptFlags::empty()), |acc, flag| {
+            let flag = match *flag {
+                f if f == always_trust() => EncryptFlags::ALWAYS_TRUST,
+                f if f == no_encrypt_to() => EncryptFlags::NO_ENCRYPT_TO,
+                f if f == prepare() => EncryptFlags::PREPARE,
+                f if f == expect_sign() => EncryptFlags::EXPECT_SIGN,
+                f if f == no_compress() => EncryptFlags::NO_COMPRESS,
+                f if f == symmetric() => EncryptFlags::SYMMETRIC,
+                f if f == throw_keyids() => EncryptFlags::THROW_KEYIDS,
+                _ => return Err(ArgError::Invalid("unsupported encrypt flag")),
+            };
+            acc.map(|a| a | flag)
+        })
+    }
+}
+
+/// Look up every recipient fingerprint on the context.
+pub(crate) fn recipient_keys(
+    context: &mut Context,
+    fingerprints: &[String],
+) -> Result<Vec<gpgme::Key>, gpgme::Error> {
+    fingerprints
+        .iter()
+        .map(|fingerprint| {
+            context
+                .get_key(fingerprint)
+                .map_err(error::not_found_on_eof)
+        })
+        .collect()
+}
+
+/// Encrypt data for a list of recipient fingerprints. An empty list
+/// together with the `symmetric` flag encrypts with the passphrase only.
 pub fn encrypt<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let fingerprint: String = args[1].decode()?;
-    let data: String = args[2].decode()?;
-    let mut context = res.0.lock().unwrap();
-    let key = context.get_key(&fingerprint).unwrap();
+    let passphrase: Passphrase = args[1].decode()?;
+    let fingerprints: Vec<String> = args[2].decode()?;
+    let data: Binary = args[3].decode()?;
+    let flags = match encrypt_flags::from_term(args[4]) {
+        Ok(flags) => flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+    let keys = match recipient_keys(&mut context, &fingerprints) {
+        Ok(keys) => keys,
+        Err(err) => return error::as_term(env, err),
+    };
     let mut encrypted = Vec::new();
-    context
-        .encrypt_with_flags(
-            Some(&key),
-            data,
+    let result = with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.encrypt_with_flags(&keys, data.as_slice(), &mut encrypted, flags)
+    });
+    match result {
+        Ok(_) => {
+            let ciphertext = binary::to_term(env, &encrypted)?;
+            Ok((atoms::ok(), ciphertext).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+pub fn encrypt_symmetric<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let data: Binary = args[2].decode()?;
+    let mut context = error::lock(&res.0)?;
+    let mut encrypted = Vec::new();
+    let result = with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.encrypt_symmetric_with_flags(
+            data.as_slice(),
             &mut encrypted,
             gpgme::EncryptFlags::ALWAYS_TRUST,
         )
-        .unwrap();
-    let ascii = String::from_utf8(encrypted).unwrap().encode(env);
-    Ok((atoms::ok(), ascii).encode(env))
+    });
+    match result {
+        Ok(_) => {
+            let ciphertext = binary::to_term(env, &encrypted)?;
+            Ok((atoms::ok(), ciphertext).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
 }
 
-pub fn encrypt_symmetric<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+pub fn decrypt<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let passphrase_str: String = args[1].decode()?;
-    let passphrase: &[u8] = passphrase_str.as_bytes();
-    let data: String = args[2].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let passphrase: Passphrase = args[1].decode()?;
+    let ciphertext: Binary = args[2].decode()?;
+    let mut context = error::lock(&res.0)?;
+    let mut plaintext = Vec::new();
+    let result = with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.decrypt(ciphertext.as_slice(), &mut plaintext)
+    });
+    match result {
+        Ok(_) => {
+            let plaintext = binary::to_term(env, &plaintext)?;
+            Ok((atoms::ok(), plaintext).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+pub(crate) mod sign_mode {
+    use error::{ArgError, ArgResult};
+    use gpgme::SignMode;
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom normal;
+        atom detached;
+        atom clear;
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<SignMode> {
+        let input: types::atom::Atom = term.decode()?;
+        match input {
+            _ if input == normal() => Ok(SignMode::Normal),
+            _ if input == detached() => Ok(SignMode::Detached),
+            _ if input == clear() => Ok(SignMode::Clear),
+            _ => Err(ArgError::Invalid("unsupported sign mode")),
+        }
+    }
+}
+
+pub(crate) mod signing_result {
+    use atoms;
+    use error;
+    use gpgme::SigningResult;
+    use rustler::{Encoder, Env, NifResult, Term};
+
+    rustler_atoms! {
+        atom fingerprint;
+        atom hash_algorithm;
+        atom key_algorithm;
+        atom class;
+        atom creation_time;
+        atom reason;
+        atom new_signatures;
+        atom invalid_signers;
+    }
+
+    pub fn as_map<'a>(env: Env<'a>, result: SigningResult) -> NifResult<Term<'a>> {
+        let mut new_signatures = Vec::<Term<'a>>::new();
+
+        for signature in result.new_signatures() {
+            let mut map = Term::map_new(env);
+
+            map = map.map_put(
+                fingerprint().encode(env),
+                signature
+                    .fingerprint()
+                    .map(|f| f.encode(env))
+                    .unwrap_or(atoms::none().encode(env)),
+            )?;
+
+            map = map.map_put(
+                hash_algorithm().encode(env),
+                signature
+                    .hash_algorithm()
+                    .name()
+                    .map(|s| s.encode(env))
+                    .unwrap_or(atoms::unknown().encode(env)),
+            )?;
+
+            map = map.map_put(
+                key_algorithm().encode(env),
+                signature
+                    .key_algorithm()
+                    .name()
+                    .map(|s| s.encode(env))
+                    .unwrap_or(atoms::unknown().encode(env)),
+            )?;
+
+            map = map.map_put(class().encode(env), signature.signature_class().encode(env))?;
+
+            map = map.map_put(
+                creation_time().encode(env),
+                signature
+                    .creation_time()
+                    .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                    .map(|t| t.as_secs().encode(env))
+                    .unwrap_or(atoms::unknown().encode(env)),
+            )?;
+
+            new_signatures.push(map)
+        }
+
+        let mut invalid_signers = Vec::<Term<'a>>::new();
+
+        for signer in result.invalid_signers() {
+            let mut map = Term::map_new(env);
+
+            map = map.map_put(
+                fingerprint().encode(env),
+                signer
+                    .fingerprint()
+                    .map(|f| f.encode(env))
+                    .unwrap_or(atoms::none().encode(env)),
+            )?;
+
+            map = map.map_put(
+                reason().encode(env),
+                signer
+                    .reason()
+                    .map(|e| error::code::from(e).encode(env))
+                    .unwrap_or(atoms::none().encode(env)),
+            )?;
+
+            invalid_signers.push(map)
+        }
+
+        let mut map = Term::map_new(env);
+        map = map.map_put(new_signatures().encode(env), new_signatures.encode(env))?;
+        map = map.map_put(invalid_signers().encode(env), invalid_signers.encode(env))?;
+        Ok(map)
+    }
+}
+
+/// Sign data with the signers and signature notations configured on the
+/// context. Returns the signature along with a map describing it.
+pub fn sign<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let mode = match sign_mode::from_term(args[2]) {
+        Ok(mode) => mode,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let data: Binary = args[3].decode()?;
+    let mut context = error::lock(&res.0)?;
+    let mut signature = Vec::new();
+    let result = with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.sign(mode, data.as_slice(), &mut signature)
+    });
+    match result {
+        Ok(result) => {
+            let signature = binary::to_term(env, &signature)?;
+            let result_map = signing_result::as_map(env, result)?;
+            Ok((atoms::ok(), signature, result_map).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+pub(crate) mod verification_result {
+    use atoms;
+    use error;
+    use gpgme::{Signature, SignatureSummary, VerificationResult};
+    use key::validity;
+    use rustler::{Encoder, Env, NifResult, Term};
+
+    mod summary {
+        use gpgme::SignatureSummary;
+
+        rustler_atoms! {
+            atom valid;
+            atom green;
+            atom red;
+            atom key_revoked;
+            atom key_expired;
+            atom sig_expired;
+            atom key_missing;
+            atom crl_missing;
+            atom crl_too_old;
+            atom bad_policy;
+            atom sys_error;
+            atom tofu_conflict;
+        }
+
+        pub fn as_list(summary: SignatureSummary) -> Vec<rustler::types::atom::Atom> {
+            let flags = [
+                (SignatureSummary::VALID, valid()),
+                (SignatureSummary::GREEN, green()),
+                (SignatureSummary::RED, red()),
+                (SignatureSummary::KEY_REVOKED, key_revoked()),
+                (SignatureSummary::KEY_EXPIRED, key_expired()),
+                (SignatureSummary::SIG_EXPIRED, sig_expired()),
+                (SignatureSummary::KEY_MISSING, key_missing()),
+                (SignatureSummary::CRL_MISSING, crl_missing()),
+                (SignatureSummary::CRL_TOO_OLD, crl_too_old()),
+                (SignatureSummary::BAD_POLICY, bad_policy()),
+                (SignatureSummary::SYS_ERROR, sys_error()),
+                (SignatureSummary::TOFU_CONFLICT, tofu_conflict()),
+            ];
+
+            flags
+                .iter()
+                .filter(|(flag, _)| summary.contains(*flag))
+                .map(|(_, atom)| *atom)
+                .collect()
+        }
+    }
+
+    mod pka_trust {
+        use gpgme::PkaTrust;
+
+        rustler_atoms! {
+            atom unknown;
+            atom bad;
+            atom okay;
+        }
+
+        pub fn from(trust: PkaTrust) -> rustler::types::atom::Atom {
+            match trust {
+                PkaTrust::Bad => bad(),
+                PkaTrust::Okay => okay(),
+                _ => unknown(),
+            }
+        }
+    }
+
+    rustler_atoms! {
+        atom fingerprint;
+        atom status;
+        atom summary;
+        atom validity;
+        atom validity_reason;
+        atom creation_time;
+        atom expiration_time;
+        atom hash_algorithm;
+        atom key_algorithm;
+        atom wrong_key_usage;
+        atom chain_model;
+        atom pka_trust;
+        atom pka_address;
+        atom tofu_conflict;
+        atom policy_url;
+        atom notations;
+    }
+
+    fn signature_as_map<'a>(env: Env<'a>, signature: Signature) -> NifResult<Term<'a>> {
+        let mut map = Term::map_new(env);
+
+        map = map.map_put(
+            fingerprint().encode(env),
+            signature
+                .fingerprint()
+                .map(|f| f.encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
+        let signature_status = match signature.status() {
+            Ok(_) => atoms::ok(),
+            Err(err) => error::code::from(err),
+        };
+
+        map = map.map_put(status().encode(env), signature_status.encode(env))?;
+
+        map = map.map_put(
+            summary().encode(env),
+            summary::as_list(signature.summary()).encode(env),
+        )?;
+
+        map = map.map_put(
+            validity().encode(env),
+            validity::from(signature.validity()).encode(env),
+        )?;
+
+        map = map.map_put(
+            validity_reason().encode(env),
+            signature
+                .nonvalidity_reason()
+                .map(|e| error::code::from(e).encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
+        map = map.map_put(
+            creation_time().encode(env),
+            signature
+                .creation_time()
+                .map(|t| {
+                    t.duration_since(std::time::SystemTime::UNIX_EPOCH)
+                        .map(|t| t.as_secs().encode(env))
+                        .unwrap_or(atoms::unknown().encode(env))
+                })
+                .unwrap_or(atoms::unknown().encode(env)),
+        )?;
+
+        map = map.map_put(
+            expiration_time().encode(env),
+            signature
+                .expiration_time()
+                .map(|t| {
+                    t.duration_since(std::time::SystemTime::UNIX_EPOCH)
+                        .map(|t| t.as_secs().encode(env))
+                        .unwrap_or(atoms::unknown().encode(env))
+                })
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
+        map = map.map_put(
+            hash_algorithm().encode(env),
+            signature
+                .hash_algorithm()
+                .name()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::unknown().encode(env)),
+        )?;
+
+        map = map.map_put(
+            key_algorithm().encode(env),
+            signature
+                .key_algorithm()
+                .name()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::unknown().encode(env)),
+        )?;
+
+        map = map.map_put(
+            wrong_key_usage().encode(env),
+            signature.is_wrong_key_usage().encode(env),
+        )?;
+
+        map = map.map_put(
+            chain_model().encode(env),
+            signature.verified_by_chain().encode(env),
+        )?;
+
+        map = map.map_put(
+            pka_trust().encode(env),
+            pka_trust::from(signature.pka_trust()).encode(env),
+        )?;
+
+        map = map.map_put(
+            pka_address().encode(env),
+            signature
+                .pka_address()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
+        map = map.map_put(
+            tofu_conflict().encode(env),
+            signature
+                .summary()
+                .contains(SignatureSummary::TOFU_CONFLICT)
+                .encode(env),
+        )?;
+
+        map = map.map_put(
+            policy_url().encode(env),
+            signature
+                .policy_url()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
+        let mut signature_notations = Vec::<Term<'a>>::new();
+
+        for notation in signature.notations() {
+            signature_notations.push(super::signature_notation::as_map(env, notation)?)
+        }
+
+        map = map.map_put(notations().encode(env), signature_notations.encode(env))?;
+
+        Ok(map)
+    }
+
+    pub fn as_list<'a>(env: Env<'a>, result: VerificationResult) -> NifResult<Term<'a>> {
+        let mut signatures = Vec::<Term<'a>>::new();
+
+        for signature in result.signatures() {
+            signatures.push(signature_as_map(env, signature)?)
+        }
+
+        Ok(signatures.encode(env))
+    }
+}
+
+mod decryption_result {
+    use atoms;
+    use error;
+    use gpgme::DecryptionResult;
+    use rustler::{Encoder, Env, NifResult, Term};
+
+    rustler_atoms! {
+        atom key_id;
+        atom algorithm;
+        atom status;
+        atom recipients;
+        atom symmetric_algorithm;
+        atom unsupported_algorithm;
+        atom filename;
+        atom wrong_key_usage;
+    }
+
+    pub fn as_map<'a>(env: Env<'a>, result: DecryptionResult) -> NifResult<Term<'a>> {
+        let mut recipient_list = Vec::<Term<'a>>::new();
+
+        for recipient in result.recipients() {
+            let mut map = Term::map_new(env);
+
+            map = map.map_put(
+                key_id().encode(env),
+                recipient
+                    .key_id()
+                    .map(|s| s.encode(env))
+                    .unwrap_or(atoms::none().encode(env)),
+            )?;
+
+            map = map.map_put(
+                algorithm().encode(env),
+                recipient
+                    .algorithm()
+                    .name()
+                    .map(|s| s.encode(env))
+                    .unwrap_or(atoms::unknown().encode(env)),
+            )?;
+
+            let recipient_status = match recipient.status() {
+                Ok(_) => atoms::ok(),
+                Err(err) => error::code::from(err),
+            };
+
+            map = map.map_put(status().encode(env), recipient_status.encode(env))?;
+
+            recipient_list.push(map)
+        }
+
+        let mut map = Term::map_new(env);
+
+        map = map.map_put(recipients().encode(env), recipient_list.encode(env))?;
+
+        map = map.map_put(
+            symmetric_algorithm().encode(env),
+            result
+                .symmetric_key_algorithm()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::unknown().encode(env)),
+        )?;
+
+        map = map.map_put(
+            unsupported_algorithm().encode(env),
+            result
+                .unsupported_algorithm()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
+        map = map.map_put(
+            filename().encode(env),
+            result
+                .filename()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
+        map = map.map_put(
+            wrong_key_usage().encode(env),
+            result.is_wrong_key_usage().encode(env),
+        )?;
+
+        Ok(map)
+    }
+}
+
+mod decrypt_verify_result {
+    rustler_atoms! {
+        atom signatures;
+        atom unsigned;
+    }
+}
+
+/// Sign data with the context's signers and encrypt it for the given
+/// recipients in a single pass.
+pub fn encrypt_sign<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let fingerprints: Vec<String> = args[2].decode()?;
+    let data: Binary = args[3].decode()?;
+    let flags = match encrypt_flags::from_term(args[4]) {
+        Ok(flags) => flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+    let keys = match recipient_keys(&mut context, &fingerprints) {
+        Ok(keys) => keys,
+        Err(err) => return error::as_term(env, err),
+    };
     let mut encrypted = Vec::new();
-    context.with_passphrase_provider(
-        |_req: gpgme::PassphraseRequest, out: &mut Write| match out.write_all(passphrase) {
-            Ok(()) => Ok(()),
-            Err(_) => Err(gpgme::Error::from_code(32)),
-        },
-        |ctx| {
-            ctx.encrypt_symmetric_with_flags(
-                data,
-                &mut encrypted,
-                gpgme::EncryptFlags::ALWAYS_TRUST,
-            )
-            .unwrap();
-        },
-    );
-    let ascii = String::from_utf8(encrypted).unwrap().encode(env);
-    Ok((atoms::ok(), ascii).encode(env))
+    let result = with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.sign_and_encrypt_with_flags(&keys, data.as_slice(), &mut encrypted, flags)
+    });
+    match result {
+        Ok((_encryption_result, signing_result)) => {
+            let ciphertext = binary::to_term(env, &encrypted)?;
+            let result_map = signing_result::as_map(env, signing_result)?;
+            Ok((atoms::ok(), ciphertext, result_map).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
 }
 
-pub fn decrypt<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+/// Decrypt a message and verify any signatures it carries. The returned
+/// map merges the decryption result with the list of signatures.
+pub fn decrypt_verify<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let passphrase_str: String = args[1].decode()?;
-    let ciphertext_str: String = args[2].decode()?;
-    let passphrase: &[u8] = passphrase_str.as_bytes();
-    let ciphertext: &[u8] = ciphertext_str.as_bytes();
-    let mut context = res.0.lock().unwrap();
+    let passphrase: Passphrase = args[1].decode()?;
+    let ciphertext: Binary = args[2].decode()?;
+    let mut context = error::lock(&res.0)?;
     let mut plaintext = Vec::new();
-    context.with_passphrase_provider(
-        |_req: gpgme::PassphraseRequest, out: &mut Write| match out.write_all(passphrase) {
-            Ok(()) => Ok(()),
-            Err(_) => Err(gpgme::Error::from_code(32)),
-        },
-        |ctx| {
-            ctx.decrypt(ciphertext, &mut plaintext).unwrap();
-        },
-    );
-    let binary = String::from_utf8(plaintext).unwrap().encode(env);
-    Ok((atoms::ok(), binary).encode(env))
+    let result = with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.decrypt_and_verify(ciphertext.as_slice(), &mut plaintext)
+    });
+    match result {
+        Ok((decryption, verification)) => {
+            let unsigned = verification.signatures().count() == 0;
+            let mut map = decryption_result::as_map(env, decryption)?;
+            map = map.map_put(
+                decrypt_verify_result::signatures().encode(env),
+                verification_result::as_list(env, verification)?,
+            )?;
+            map = map.map_put(
+                decrypt_verify_result::unsigned().encode(env),
+                unsigned.encode(env),
+            )?;
+            let plaintext = binary::to_term(env, &plaintext)?;
+            Ok((atoms::ok(), plaintext, map).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Verify a normal or clear-text signature, returning the signed
+/// plaintext alongside the list of signatures found.
+pub fn verify<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let signature: Binary = args[1].decode()?;
+    let mut context = error::lock(&res.0)?;
+    let mut plaintext = Vec::new();
+    match context.verify_opaque(signature.as_slice(), &mut plaintext) {
+        Ok(result) => {
+            let plaintext = binary::to_term(env, &plaintext)?;
+            let signatures = verification_result::as_list(env, result)?;
+            Ok((atoms::ok(), plaintext, signatures).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Verify a detached signature against the signed text.
+pub fn verify_detached<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let signature: Binary = args[1].decode()?;
+    let signed_text: Binary = args[2].decode()?;
+    let mut context = error::lock(&res.0)?;
+    match context.verify_detached(signature.as_slice(), signed_text.as_slice()) {
+        Ok(result) => {
+            let signatures = verification_result::as_list(env, result)?;
+            Ok((atoms::ok(), atoms::none(), signatures).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
 }
 
 pub fn find_key<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let mut context = error::lock(&res.0)?;
     let query: String = args[1].decode()?;
     match context.get_key(query) {
         Ok(key) => {
             let wrapped: ResourceArc<GpgmeKey> = ResourceArc::new(key.into());
             Ok((atoms::ok(), wrapped).encode(env))
         }
-        Err(_err) => Err(rustler::Error::Atom("not_found")),
+        Err(err) => error::as_term(env, error::not_found_on_eof(err)),
     }
 }
 
 mod signature_notation {
+    use atoms;
+    use gpgme::SignatureNotation;
+    use rustler::{Encoder, Env, NifResult, Term};
+
     rustler_atoms! {
         atom name;
         atom value;
         atom critical;
         atom human_readable;
     }
-}
 
-pub fn signature_notations<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
-    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let context = res.0.lock().unwrap();
-    let mut notations: Vec<Term<'a>> = Vec::new();
-    for notation in context.signature_notations() {
+    pub fn as_map<'a>(env: Env<'a>, notation: SignatureNotation) -> NifResult<Term<'a>> {
         let mut map = Term::map_new(env);
         map = map.map_put(
-            signature_notation::name().encode(env),
+            name().encode(env),
             notation
                 .name()
                 .map(|s| s.encode(env))
                 .unwrap_or(atoms::none().encode(env)),
         )?;
         map = map.map_put(
-            signature_notation::value().encode(env),
+            value().encode(env),
             notation
                 .value()
                 .map(|s| s.encode(env))
                 .unwrap_or(atoms::none().encode(env)),
         )?;
+        map = map.map_put(critical().encode(env), notation.is_critical().encode(env))?;
         map = map.map_put(
-            signature_notation::critical().encode(env),
-            notation.is_critical().encode(env),
-        )?;
-        map = map.map_put(
-            signature_notation::human_readable().encode(env),
+            human_readable().encode(env),
             notation.is_human_readable().encode(env),
         )?;
-        notations.push(map)
+        Ok(map)
+    }
+}
+
+pub fn signature_notations<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let context = error::lock(&res.0)?;
+    let mut notations: Vec<Term<'a>> = Vec::new();
+    for notation in context.signature_notations() {
+        notations.push(signature_notation::as_map(env, notation)?)
     }
     Ok((atoms::ok(), notations).encode(env))
 }
 
 pub fn add_signature_notation<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let mut context = error::lock(&res.0)?;
     let name: String = args[1].decode()?;
     let value: String = args[2].decode()?;
     let flags: Vec<Term<'a>> = args[3].decode()?;
@@ -348,49 +1245,50 @@ pub fn add_signature_notation<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<
     // let flags = SignatureNotationFlags::HUMAN_READABLE | SignatureNotationFlags::CRITICAL;
     match context.add_signature_notation(name, value, parsed_flags) {
         Ok(_) => Ok(atoms::ok().encode(env)),
-        Err(_err) => Err(rustler::Error::Atom("error")),
+        Err(err) => error::as_term(env, err),
     }
 }
 
 pub fn clear_signature_notations<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let mut context = error::lock(&res.0)?;
     context.clear_signature_notations();
     Ok(atoms::ok().encode(env))
 }
 
 pub fn sender<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let context = res.0.lock().unwrap();
+    let context = error::lock(&res.0)?;
     match context.sender() {
         Ok(s) if s.len() == 0 => Ok(atoms::none().encode(env)),
         Ok(s) => Ok(s.encode(env)),
-        Err(_err) => Err(rustler::Error::Atom("error")),
+        Err(None) => Ok(atoms::none().encode(env)),
+        Err(Some(_)) => error::as_term(env, gpgme::Error::INV_VALUE),
     }
 }
 
 pub fn set_sender<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
     let sender: String = args[1].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let mut context = error::lock(&res.0)?;
     match context.set_sender(sender) {
         Ok(_) => Ok(atoms::ok().encode(env)),
-        Err(_err) => Err(rustler::Error::Atom("error")),
+        Err(err) => error::as_term(env, err),
     }
 }
 
 pub fn clear_sender<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let mut context = error::lock(&res.0)?;
     match context.clear_sender() {
         Ok(_) => Ok(atoms::ok().encode(env)),
-        Err(_err) => Err(rustler::Error::Atom("error")),
+        Err(err) => error::as_term(env, err),
     }
 }
 
 pub fn signers<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let context = res.0.lock().unwrap();
+    let context = error::lock(&res.0)?;
     let mut signers: Vec<Term<'a>> = Vec::new();
     for signer in context.signers() {
         let key: ResourceArc<GpgmeKey> = ResourceArc::new(signer.into());
@@ -402,16 +1300,16 @@ pub fn signers<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
 pub fn add_signer<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
     let signer: ResourceArc<GpgmeKey> = args[1].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let mut context = error::lock(&res.0)?;
     match context.add_signer(&signer.0) {
         Ok(_) => Ok(atoms::ok().encode(env)),
-        Err(_) => Err(rustler::Error::Atom("error")),
+        Err(err) => error::as_term(env, err),
     }
 }
 
 pub fn clear_signers<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let mut context = res.0.lock().unwrap();
+    let mut context = error::lock(&res.0)?;
     context.clear_signers();
     Ok(atoms::ok().encode(env))
 }
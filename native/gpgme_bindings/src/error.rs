@@ -0,0 +1,212 @@
+use atoms;
+use gpgme::Error;
+use rustler::types::atom::Atom;
+use rustler::{Encoder, Env, NifResult, Term};
+use std::sync::{Mutex, MutexGuard};
+
+mod keys {
+    rustler_atoms! {
+        atom code;
+        atom source;
+        atom message;
+        atom struct_ = "__struct__";
+        atom exception = "__exception__";
+        atom ex_gpgme_error = "Elixir.ExGpgme.Error";
+    }
+}
+
+pub(crate) mod code {
+    use gpgme::Error;
+    use rustler::types::atom::Atom;
+
+    rustler_atoms! {
+        atom general;
+        atom bad_passphrase;
+        atom no_passphrase;
+        atom no_pubkey;
+        atom no_seckey;
+        atom unusable_pubkey;
+        atom unusable_seckey;
+        atom decrypt_failed;
+        atom bad_signature;
+        atom no_data;
+        atom not_found;
+        atom ambiguous_name;
+        atom canceled;
+        atom invalid_value;
+        atom invalid_engine;
+        atom unsupported_protocol;
+        atom not_supported;
+        atom conflict;
+        atom key_expired;
+        atom cert_revoked;
+        atom timeout;
+        atom eof;
+        atom permission_denied;
+        atom already_exists;
+        atom poisoned;
+        atom out_of_memory;
+        atom unknown;
+    }
+
+    pub fn from(err: Error) -> Atom {
+        let codes = [
+            (Error::GENERAL, general()),
+            (Error::BAD_PASSPHRASE, bad_passphrase()),
+            (Error::NO_PASSPHRASE, no_passphrase()),
+            (Error::NO_PUBKEY, no_pubkey()),
+            (Error::NO_SECKEY, no_seckey()),
+            (Error::UNUSABLE_PUBKEY, unusable_pubkey()),
+            (Error::UNUSABLE_SECKEY, unusable_seckey()),
+            (Error::DECRYPT_FAILED, decrypt_failed()),
+            (Error::BAD_SIGNATURE, bad_signature()),
+            (Error::NO_DATA, no_data()),
+            (Error::NOT_FOUND, not_found()),
+            (Error::AMBIGUOUS_NAME, ambiguous_name()),
+            (Error::CANCELED, canceled()),
+            (Error::INV_VALUE, invalid_value()),
+            (Error::INV_ENGINE, invalid_engine()),
+            (Error::UNSUPPORTED_PROTOCOL, unsupported_protocol()),
+            (Error::NOT_SUPPORTED, not_supported()),
+            (Error::CONFLICT, conflict()),
+            (Error::KEY_EXPIRED, key_expired()),
+            (Error::CERT_REVOKED, cert_revoked()),
+            (Error::TIMEOUT, timeout()),
+            (Error::EOF, eof()),
+            (Error::ENOENT, not_found()),
+            (Error::EACCES, permission_denied()),
+            (Error::EEXIST, already_exists()),
+        ];
+
+        codes
+            .iter()
+            .find(|(e, _)| e.code() == err.code())
+            .map(|(_, atom)| *atom)
+            .unwrap_or(unknown())
+    }
+}
+
+mod source {
+    use gpgme::Error;
+    use rustler::types::atom::Atom;
+
+    rustler_atoms! {
+        atom user;
+        atom ex_gpgme;
+        atom gpgme;
+        atom gpg;
+        atom gpgsm;
+        atom gpg_agent;
+        atom pinentry;
+        atom scd;
+        atom keybox;
+        atom dirmngr;
+        atom unknown;
+    }
+
+    pub fn from(err: Error) -> Atom {
+        match err.source() {
+            Some("GPGME") => gpgme(),
+            Some("GnuPG") => gpg(),
+            Some("GpgSM") => gpgsm(),
+            Some("GPG Agent") => gpg_agent(),
+            Some("Pinentry") => pinentry(),
+            Some("SCD") => scd(),
+            Some("Keybox") => keybox(),
+            Some("Dirmngr") => dirmngr(),
+            _ => unknown(),
+        }
+    }
+}
+
+/// `gpgme` reports lookups that match nothing as end-of-file. Callers
+/// asking for a single key expect `:not_found` instead.
+pub fn not_found_on_eof(err: Error) -> Error {
+    if err.code() == Error::EOF.code() {
+        Error::NOT_FOUND
+    } else {
+        err
+    }
+}
+
+/// Convert an I/O failure, e.g. opening a file, into a `gpgme::Error`.
+/// Missing files become `:not_found`, see `code::from`.
+pub fn from_io(err: std::io::Error) -> Error {
+    err.raw_os_error()
+        .map(Error::from_errno)
+        .unwrap_or(Error::GENERAL)
+}
+
+fn as_map<'a>(env: Env<'a>, code: Atom, source: Atom, message: &str) -> NifResult<Term<'a>> {
+    let mut map = Term::map_new(env);
+    map = map.map_put(keys::code().encode(env), code.encode(env))?;
+    map = map.map_put(keys::source().encode(env), source.encode(env))?;
+    map = map.map_put(keys::message().encode(env), message.encode(env))?;
+    Ok(map)
+}
+
+/// Encode a `gpgme::Error` as `{:error, %{code: atom, source: atom, message: binary}}`.
+pub fn as_term<'a>(env: Env<'a>, err: Error) -> NifResult<Term<'a>> {
+    let map = as_map(env, code::from(err), source::from(err), &err.description())?;
+    Ok((atoms::error(), map).encode(env))
+}
+
+/// Failure to decode an argument. Terms of the wrong type raise `badarg`,
+/// terms naming something this binding does not support, e.g. an unknown
+/// flag, are returned as errors, see `invalid_argument`.
+pub enum ArgError {
+    BadArg,
+    Invalid(&'static str),
+}
+
+impl From<rustler::Error> for ArgError {
+    fn from(_err: rustler::Error) -> ArgError {
+        ArgError::BadArg
+    }
+}
+
+pub type ArgResult<T> = Result<T, ArgError>;
+
+/// Raise `badarg`, or return
+/// `{:error, %{code: :invalid_value, source: :user, message: binary}}`
+/// naming the unsupported value.
+pub fn invalid_argument<'a>(env: Env<'a>, err: ArgError) -> NifResult<Term<'a>> {
+    match err {
+        ArgError::BadArg => Err(rustler::Error::BadArg),
+        ArgError::Invalid(message) => {
+            let map = as_map(env, code::invalid_value(), source::user(), message)?;
+            Ok((atoms::error(), map).encode(env))
+        }
+    }
+}
+
+/// An `ExGpgme.Error` raised for failures of the binding itself, where
+/// no error tuple can be returned.
+struct Raised(Atom, &'static str);
+
+impl Encoder for Raised {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        as_map(env, self.0, source::ex_gpgme(), self.1)
+            .and_then(|map| {
+                map.map_put(
+                    keys::struct_().encode(env),
+                    keys::ex_gpgme_error().encode(env),
+                )
+            })
+            .and_then(|map| map.map_put(keys::exception().encode(env), true.encode(env)))
+            .unwrap_or_else(|_| self.0.encode(env))
+    }
+}
+
+/// Raise `ExGpgme.Error` with the given code and message.
+pub fn raise(code: Atom, message: &'static str) -> rustler::Error {
+    rustler::Error::RaiseTerm(Box::new(Raised(code, message)))
+}
+
+/// Lock a mutex shared between NIF calls. A lock that was held while
+/// another call panicked raises instead of panicking again.
+pub fn lock<T>(mutex: &Mutex<T>) -> NifResult<MutexGuard<T>> {
+    mutex
+        .lock()
+        .map_err(|_| raise(code::poisoned(), "lock poisoned by an earlier panic"))
+}
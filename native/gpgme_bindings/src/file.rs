@@ -0,0 +1,193 @@
+use atoms;
+use context::{self, sign_mode, signing_result, verification_result, GpgmeContext};
+use error::{self, ArgError, ArgResult};
+use gpgme::Data;
+use passphrase::Passphrase;
+use rustler::resource::ResourceArc;
+use rustler::types::atom::Atom;
+use rustler::{Encoder, Env, NifResult, Term};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+/// Open a file for gpgme to read from. The file is read on demand through
+/// callbacks, unlike `Data::load` which copies it into memory first.
+fn input(path: &str) -> Result<Data<'static>, gpgme::Error> {
+    let file = File::open(path).map_err(error::from_io)?;
+    let mut data = Data::from_seekable_reader(file).map_err(|e| e.error())?;
+    if let Some(name) = Path::new(path).file_name().and_then(|n| n.to_str()) {
+        data.set_file_name(name)?;
+    }
+    Ok(data)
+}
+
+/// Create a file for gpgme to write its output to. Existing files are
+/// never overwritten, so the output can be removed again on errors.
+fn output(path: &str) -> Result<Data<'static>, gpgme::Error> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(error::from_io)?;
+    Data::from_writer(file).map_err(|e| e.error())
+}
+
+/// Reject writing the output over the input.
+fn distinct(source: &str, destination: &str) -> ArgResult<()> {
+    let same = match (fs::canonicalize(source), fs::canonicalize(destination)) {
+        (Ok(source), Ok(destination)) => source == destination,
+        _ => Path::new(source) == Path::new(destination),
+    };
+    if same {
+        Err(ArgError::Invalid(
+            "source and destination are the same file",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Decode an optional path, passed as `:none` when absent.
+fn optional_path<'a>(term: Term<'a>) -> NifResult<Option<String>> {
+    let atom: NifResult<Atom> = term.decode();
+    match atom {
+        Ok(atom) if atom == atoms::none() => Ok(None),
+        _ => term.decode().map(Some),
+    }
+}
+
+/// Do not leave partial output behind when an operation fails. Only
+/// called once `output` has created `destination`.
+fn cleanup<T>(result: Result<T, gpgme::Error>, destination: &str) -> Result<T, gpgme::Error> {
+    if result.is_err() {
+        let _ = fs::remove_file(destination);
+    }
+    result
+}
+
+pub fn encrypt<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let fingerprints: Vec<String> = args[2].decode()?;
+    let source: String = args[3].decode()?;
+    let destination: String = args[4].decode()?;
+    let flags = match context::encrypt_flags::from_term(args[5]) {
+        Ok(flags) => flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    if let Err(err) = distinct(&source, &destination) {
+        return error::invalid_argument(env, err);
+    }
+    let mut context = error::lock(&res.0)?;
+
+    let result = context::recipient_keys(&mut context, &fingerprints).and_then(|keys| {
+        let mut plaintext = input(&source)?;
+        let mut ciphertext = output(&destination)?;
+        cleanup(
+            context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+                ctx.encrypt_with_flags(&keys, &mut plaintext, &mut ciphertext, flags)
+            }),
+            &destination,
+        )
+    });
+
+    match result {
+        Ok(_) => Ok(atoms::ok().encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+pub fn decrypt<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let source: String = args[2].decode()?;
+    let destination: String = args[3].decode()?;
+    if let Err(err) = distinct(&source, &destination) {
+        return error::invalid_argument(env, err);
+    }
+    let mut context = error::lock(&res.0)?;
+
+    let result = input(&source).and_then(|mut ciphertext| {
+        let mut plaintext = output(&destination)?;
+        cleanup(
+            context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+                ctx.decrypt(&mut ciphertext, &mut plaintext)
+            }),
+            &destination,
+        )
+    });
+
+    match result {
+        Ok(_) => Ok(atoms::ok().encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+pub fn sign<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let mode = match sign_mode::from_term(args[2]) {
+        Ok(mode) => mode,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let source: String = args[3].decode()?;
+    let destination: String = args[4].decode()?;
+    if let Err(err) = distinct(&source, &destination) {
+        return error::invalid_argument(env, err);
+    }
+    let mut context = error::lock(&res.0)?;
+
+    let result = input(&source).and_then(|mut plaintext| {
+        let mut signature = output(&destination)?;
+        cleanup(
+            context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+                ctx.sign(mode, &mut plaintext, &mut signature)
+            }),
+            &destination,
+        )
+    });
+
+    match result {
+        Ok(result) => Ok((atoms::ok(), signing_result::as_map(env, result)?).encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Verify the signature in `source`. A detached signature is checked
+/// against `signed`, otherwise the signed plaintext is written to
+/// `destination` if given.
+pub fn verify<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let source: String = args[1].decode()?;
+    let signed = optional_path(args[2])?;
+    let destination = optional_path(args[3])?;
+    if let Some(ref destination) = destination {
+        if let Err(err) = distinct(&source, destination) {
+            return error::invalid_argument(env, err);
+        }
+    }
+    let mut context = error::lock(&res.0)?;
+
+    let result = input(&source).and_then(|mut signature| match (signed, destination) {
+        (Some(signed), _) => {
+            let mut signed_text = input(&signed)?;
+            context.verify_detached(&mut signature, &mut signed_text)
+        }
+        (None, Some(destination)) => {
+            let mut plaintext = output(&destination)?;
+            cleanup(
+                context.verify_opaque(&mut signature, &mut plaintext),
+                &destination,
+            )
+        }
+        (None, None) => {
+            let mut plaintext = Data::from_writer(io::sink()).map_err(|e| e.error())?;
+            context.verify_opaque(&mut signature, &mut plaintext)
+        }
+    });
+
+    match result {
+        Ok(result) => Ok((atoms::ok(), verification_result::as_list(env, result)?).encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
@@ -0,0 +1,60 @@
+use gpgme::Interactor;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
+
+/// Drives gpg's interactive key editing for operations gpgme has no
+/// direct call for. Line prompts are answered from the queue registered
+/// for their keyword, yes/no questions are confirmed only if their keyword
+/// was. Any other question cancels the edit. Once the commands for
+/// `keyedit.prompt` run out the changes are saved.
+pub(crate) struct Script {
+    answers: HashMap<&'static str, VecDeque<String>>,
+    confirmed: HashSet<&'static str>,
+}
+
+impl Script {
+    pub fn new() -> Script {
+        Script {
+            answers: HashMap::new(),
+            confirmed: HashSet::new(),
+        }
+    }
+
+    pub fn answer<S: Into<String>>(mut self, keyword: &'static str, answer: S) -> Script {
+        self.answers
+            .entry(keyword)
+            .or_insert_with(VecDeque::new)
+            .push_back(answer.into());
+        self
+    }
+
+    pub fn confirm(mut self, keyword: &'static str) -> Script {
+        self.confirmed.insert(keyword);
+        self
+    }
+}
+
+impl Interactor for Script {
+    fn interact<W: Write>(
+        &mut self,
+        status: &str,
+        args: Option<&str>,
+        out: Option<W>,
+    ) -> Result<(), gpgme::Error> {
+        let keyword = args.unwrap_or("");
+        let answer = match status {
+            "GET_BOOL" if self.confirmed.contains(keyword) => "y".to_owned(),
+            "GET_BOOL" => return Err(gpgme::Error::CANCELED),
+            "GET_LINE" => match self.answers.get_mut(keyword).and_then(|a| a.pop_front()) {
+                Some(answer) => answer,
+                None if keyword == "keyedit.prompt" => "save".to_owned(),
+                None => return Err(gpgme::Error::CANCELED),
+            },
+            _ => return Ok(()),
+        };
+        match out {
+            Some(mut out) => writeln!(out, "{}", answer).map_err(|_| gpgme::Error::GENERAL),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use atoms;
-use context::GpgmeContext;
+use context::{self, GpgmeContext};
+use error;
 use gpgme::KeyListMode;
 use rustler::resource::ResourceArc;
 use rustler::{Encoder, Env, NifResult, Term};
@@ -30,7 +31,7 @@ mod signature {
     }
 }
 
-mod validity {
+pub(crate) mod validity {
     use gpgme::Validity;
 
     rustler_atoms! {
@@ -82,8 +83,137 @@ mod key_origin {
     }
 }
 
+pub(crate) mod tofu_policy {
+    use error::{ArgError, ArgResult};
+    use gpgme::TofuPolicy;
+    use rustler::types::atom::Atom;
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom none;
+        atom auto;
+        atom good;
+        atom unknown;
+        atom bad;
+        atom ask;
+    }
+
+    pub fn from(policy: TofuPolicy) -> Atom {
+        match policy {
+            TofuPolicy::None => none(),
+            TofuPolicy::Auto => auto(),
+            TofuPolicy::Good => good(),
+            TofuPolicy::Unknown => unknown(),
+            TofuPolicy::Bad => bad(),
+            TofuPolicy::Ask => ask(),
+            _ => unknown(),
+        }
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<TofuPolicy> {
+        let policy: types::atom::Atom = term.decode()?;
+        match policy {
+            p if p == auto() => Ok(TofuPolicy::Auto),
+            p if p == good() => Ok(TofuPolicy::Good),
+            p if p == unknown() => Ok(TofuPolicy::Unknown),
+            p if p == bad() => Ok(TofuPolicy::Bad),
+            p if p == ask() => Ok(TofuPolicy::Ask),
+            _ => Err(ArgError::Invalid("unsupported tofu policy")),
+        }
+    }
+}
+
+mod tofu_info {
+    use atoms;
+    use gpgme::TofuInfo;
+    use rustler::{Encoder, Env, NifResult, Term};
+    use std::time::SystemTime;
+
+    rustler_atoms! {
+        atom validity;
+        atom policy;
+        atom signature_count;
+        atom encrypted_count;
+        atom signature_first_seen;
+        atom signature_last_seen;
+        atom encrypted_first_seen;
+        atom encrypted_last_seen;
+        atom description;
+        atom conflict;
+        atom no_history;
+        atom little_history;
+        atom basic_history;
+        atom large_history;
+    }
+
+    fn validity_from(validity: u32) -> rustler::types::atom::Atom {
+        match validity {
+            0 => conflict(),
+            1 => no_history(),
+            2 => little_history(),
+            3 => basic_history(),
+            4 => large_history(),
+            _ => atoms::unknown(),
+        }
+    }
+
+    fn timestamp<'a>(env: Env<'a>, time: Option<SystemTime>) -> Term<'a> {
+        time.and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|t| t.as_secs().encode(env))
+            .unwrap_or(atoms::none().encode(env))
+    }
+
+    /// Encode the TOFU statistics of a user id, or `:none` if the key was
+    /// not listed with the `with_tofu` mode.
+    pub fn as_term<'a>(env: Env<'a>, info: Option<TofuInfo>) -> NifResult<Term<'a>> {
+        let info = match info {
+            Some(info) => info,
+            None => return Ok(atoms::none().encode(env)),
+        };
+
+        let fields = vec![
+            (validity(), validity_from(info.validity()).encode(env)),
+            (
+                policy(),
+                super::tofu_policy::from(info.policy()).encode(env),
+            ),
+            (signature_count(), info.signature_count().encode(env)),
+            (encrypted_count(), info.encrypted_count().encode(env)),
+            (
+                signature_first_seen(),
+                timestamp(env, info.signature_first_seen()),
+            ),
+            (
+                signature_last_seen(),
+                timestamp(env, info.signature_last_seen()),
+            ),
+            (
+                encrypted_first_seen(),
+                timestamp(env, info.encrypted_first_seen()),
+            ),
+            (
+                encrypted_last_seen(),
+                timestamp(env, info.encrypted_last_seen()),
+            ),
+            (
+                description(),
+                info.description()
+                    .map(|s| s.encode(env))
+                    .unwrap_or(atoms::none().encode(env)),
+            ),
+        ];
+
+        let mut map = Term::map_new(env);
+        for (field, value) in fields {
+            map = map.map_put(field.encode(env), value)?;
+        }
+        Ok(map)
+    }
+}
+
 mod user_id {
     rustler_atoms! {
+        atom uid;
         atom name;
         atom email;
         atom comment;
@@ -96,24 +226,166 @@ mod user_id {
     }
 }
 
-pub fn list<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
-    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
-    let mut ctx = res.0.lock().unwrap();
+pub(crate) mod key_list_mode {
+    use error::{ArgError, ArgResult};
+    use gpgme::KeyListMode;
+    use rustler::{types, Term};
 
-    let mut mode = KeyListMode::empty();
-    mode.insert(KeyListMode::LOCAL);
+    rustler_atoms! {
+        atom local;
+        atom extern_ = "extern";
+        atom sigs;
+        atom sig_notations;
+        atom with_secret;
+        atom with_tofu;
+        atom validate;
+        atom ephemeral;
+        atom locate;
+    }
 
+    /// Decode a list of key listing modes. An empty list lists the local
+    /// keyring only.
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<KeyListMode> {
+        let modes: Vec<types::atom::Atom> = term.decode()?;
+        if modes.is_empty() {
+            return Ok(KeyListMode::LOCAL);
+        }
+        modes.iter().fold(Ok(KeyListMode::empty()), |acc, mode| {
+            let mode = match *mode {
+                m if m == local() => KeyListMode::LOCAL,
+                m if m == extern_() => KeyListMode::EXTERN,
+                m if m == sigs() => KeyListMode::SIGS,
+                m if m == sig_notations() => KeyListMode::SIG_NOTATIONS,
+                m if m == with_secret() => KeyListMode::WITH_SECRET,
+                m if m == with_tofu() => KeyListMode::WITH_TOFU,
+                m if m == validate() => KeyListMode::VALIDATE,
+                m if m == ephemeral() => KeyListMode::EPHEMERAL,
+                m if m == locate() => KeyListMode::LOCATE,
+                _ => return Err(ArgError::Invalid("unsupported key list mode")),
+            };
+            acc.map(|a| a | mode)
+        })
+    }
+
+    /// The atoms for the modes set in `mode`. `:locate` is reported as
+    /// `:local` and `:extern`, which it is made of.
+    pub fn as_list(mode: KeyListMode) -> Vec<types::atom::Atom> {
+        vec![
+            (KeyListMode::LOCAL, local()),
+            (KeyListMode::EXTERN, extern_()),
+            (KeyListMode::SIGS, sigs()),
+            (KeyListMode::SIG_NOTATIONS, sig_notations()),
+            (KeyListMode::WITH_SECRET, with_secret()),
+            (KeyListMode::WITH_TOFU, with_tofu()),
+            (KeyListMode::VALIDATE, validate()),
+            (KeyListMode::EPHEMERAL, ephemeral()),
+        ]
+        .into_iter()
+        .filter(|&(flag, _)| mode.contains(flag))
+        .map(|(_, atom)| atom)
+        .collect()
+    }
+}
+
+fn collect(
+    context: &mut gpgme::Context,
+    patterns: Vec<String>,
+    secret_only: bool,
+) -> Result<(Vec<ResourceArc<GpgmeKey>>, bool), gpgme::Error> {
+    let mut keys = if secret_only {
+        context.find_secret_keys(patterns)?
+    } else {
+        context.find_keys(patterns)?
+    };
     let mut key_list = vec![];
+    for key in keys.by_ref() {
+        key_list.push(ResourceArc::new(GpgmeKey(key?)));
+    }
+    let result = keys.finish()?;
+    Ok((key_list, result.is_truncated()))
+}
+
+/// List the keys matching `patterns`, or all keys if there are none,
+/// using the given key listing mode for this call only. Also returns
+/// whether gpg stopped the listing early.
+fn find(
+    context: &mut gpgme::Context,
+    patterns: Vec<String>,
+    secret_only: bool,
+    mode: KeyListMode,
+) -> Result<(Vec<ResourceArc<GpgmeKey>>, bool), gpgme::Error> {
+    let previous = context.key_list_mode();
+    context.set_key_list_mode(mode)?;
+    let result = collect(context, patterns, secret_only);
+    context.set_key_list_mode(previous)?;
+    result
+}
 
-    ctx.set_key_list_mode(mode).expect("key list mode");
-    let mut keys = ctx.keys().expect("keys");
+/// List every key using the context's own key listing mode.
+pub fn list<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let mut ctx = error::lock(&res.0)?;
+    let mode = ctx.key_list_mode();
 
-    for key in keys.by_ref().filter_map(|x| x.ok()) {
-        let key_ref = ResourceArc::new(GpgmeKey(key));
-        key_list.push(key_ref)
+    match find(&mut ctx, vec![], false, mode) {
+        Ok((key_list, _)) => Ok((atoms::ok(), key_list).encode(env)),
+        Err(err) => error::as_term(env, err),
     }
+}
+
+/// Like `list/1`, but takes search patterns, whether to list secret keys
+/// only and the key listing modes. Returns `{:ok, keys, truncated}`.
+pub fn list_matching<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let patterns: Vec<String> = args[1].decode()?;
+    let secret_only: bool = args[2].decode()?;
+    let mode = match key_list_mode::from_term(args[3]) {
+        Ok(mode) => mode,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut ctx = error::lock(&res.0)?;
+
+    match find(&mut ctx, patterns, secret_only, mode) {
+        Ok((key_list, truncated)) => Ok((atoms::ok(), key_list, truncated).encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
 
-    Ok((atoms::ok(), key_list).encode(env))
+fn locate_with(
+    context: &mut gpgme::Context,
+    email: String,
+    mechanisms: &str,
+) -> Result<gpgme::Key, gpgme::Error> {
+    if !mechanisms.is_empty() {
+        context.set_flag("auto-key-locate", mechanisms)?;
+    }
+    context.set_key_list_mode(KeyListMode::LOCATE)?;
+    let mut keys = context.find_keys(vec![email])?;
+    match keys.next() {
+        Some(key) => key,
+        None => Err(gpgme::Error::NOT_FOUND),
+    }
+}
+
+/// Look up a key by email address the way `gpg --locate-keys` does.
+/// `mechanisms` is a value for gpg's `auto-key-locate` option, e.g.
+/// `"clear,nodefault,wkd,hkp://keys.example.org"`, or empty to use gpg's
+/// configuration. The lookup runs on a separate context, so the
+/// mechanisms only apply to this call.
+pub fn locate<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let email: String = args[1].decode()?;
+    let mechanisms: String = args[2].decode()?;
+
+    let duplicate = {
+        let context = error::lock(&res.0)?;
+        context::duplicate(&context)
+    };
+
+    match duplicate.and_then(|mut ctx| locate_with(&mut ctx, email, &mechanisms)) {
+        Ok(key) => Ok((atoms::ok(), ResourceArc::new(GpgmeKey(key))).encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
 }
 
 pub fn key_id<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
@@ -191,13 +463,26 @@ pub fn key_is_qualified<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'
     Ok((atoms::ok(), is_qualified).encode(env))
 }
 
-pub fn key_user_ids<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+pub fn key_owner_trust<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
     let key: ResourceArc<GpgmeKey> = args[0].decode()?;
+    let owner_trust = validity::from(key.0.owner_trust()).encode(env);
+    Ok((atoms::ok(), owner_trust).encode(env))
+}
+
+fn user_ids_as_list<'a>(env: Env<'a>, key: &gpgme::Key) -> NifResult<Vec<Term<'a>>> {
     let mut list: Vec<Term<'a>> = Vec::new();
 
-    for user_id in key.0.user_ids() {
+    for user_id in key.user_ids() {
         let mut map = Term::map_new(env);
 
+        map = map.map_put(
+            user_id::uid().encode(env),
+            user_id
+                .id()
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        )?;
+
         map = map.map_put(
             user_id::name().encode(env),
             user_id
@@ -242,7 +527,10 @@ pub fn key_user_ids<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>>
             validity::from(user_id.validity()).encode(env),
         )?;
 
-        map = map.map_put(user_id::tofu_info().encode(env), atoms::none().encode(env))?;
+        map = map.map_put(
+            user_id::tofu_info().encode(env),
+            tofu_info::as_term(env, user_id.tofu_info())?,
+        )?;
 
         let mut signatures: Vec<Term<'a>> = Vec::new();
 
@@ -325,7 +613,12 @@ pub fn key_user_ids<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>>
 
         list.push(map)
     }
-    Ok((atoms::ok(), list).encode(env))
+    Ok(list)
+}
+
+pub fn key_user_ids<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let key: ResourceArc<GpgmeKey> = args[0].decode()?;
+    Ok((atoms::ok(), user_ids_as_list(env, &key.0)?).encode(env))
 }
 
 mod subkey {
@@ -351,11 +644,10 @@ mod subkey {
     }
 }
 
-pub fn key_subkeys<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
-    let key: ResourceArc<GpgmeKey> = args[0].decode()?;
+fn subkeys_as_list<'a>(env: Env<'a>, key: &gpgme::Key) -> NifResult<Vec<Term<'a>>> {
     let mut list: Vec<Term<'a>> = Vec::new();
 
-    for subkey in key.0.subkeys() {
+    for subkey in key.subkeys() {
         let mut map = Term::map_new(env);
 
         map = map.map_put(
@@ -467,13 +759,123 @@ pub fn key_subkeys<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
         list.push(map)
     }
 
-    Ok((atoms::ok(), list).encode(env))
+    Ok(list)
+}
+
+pub fn key_subkeys<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let key: ResourceArc<GpgmeKey> = args[0].decode()?;
+    Ok((atoms::ok(), subkeys_as_list(env, &key.0)?).encode(env))
 }
 
-// .field("revoked", &self.is_revoked())
-// .field("invalid", &self.is_invalid())
-// .field("disabled", &self.is_disabled())
-// .field("can_sign", &self.can_sign())
-// .field("can_encrypt", &self.can_encrypt())
-// .field("can_certify", &self.can_certify())
-// .field("can_auth", &self.can_authenticate())
+mod key_fields {
+    rustler_atoms! {
+        atom id;
+        atom fingerprint;
+        atom has_secret;
+        atom can_encrypt;
+        atom can_sign;
+        atom can_certify;
+        atom can_authenticate;
+        atom is_revoked;
+        atom is_expired;
+        atom is_disabled;
+        atom is_invalid;
+        atom is_qualified;
+        atom owner_trust;
+        atom origin;
+        atom last_update;
+        atom protocol;
+        atom issuer_serial;
+        atom issuer_name;
+        atom chain_id;
+        atom card_key;
+        atom card_serial_number;
+        atom user_ids;
+        atom subkeys;
+    }
+}
+
+/// Return everything about a key in one map, with the same shape as the
+/// `%ExGpgme.Key{}` struct.
+pub fn key_to_map<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeKey> = args[0].decode()?;
+    let key = &res.0;
+    let primary = key.primary_key();
+    let optional = |value: Result<&str, _>| {
+        value
+            .map(|s| s.encode(env))
+            .unwrap_or(atoms::none().encode(env))
+    };
+
+    let fields = vec![
+        (key_fields::id(), optional(key.id())),
+        (key_fields::fingerprint(), optional(key.fingerprint())),
+        (key_fields::has_secret(), key.has_secret().encode(env)),
+        (key_fields::can_encrypt(), key.can_encrypt().encode(env)),
+        (key_fields::can_sign(), key.can_sign().encode(env)),
+        (key_fields::can_certify(), key.can_certify().encode(env)),
+        (
+            key_fields::can_authenticate(),
+            key.can_authenticate().encode(env),
+        ),
+        (key_fields::is_revoked(), key.is_revoked().encode(env)),
+        (key_fields::is_expired(), key.is_expired().encode(env)),
+        (key_fields::is_disabled(), key.is_disabled().encode(env)),
+        (key_fields::is_invalid(), key.is_invalid().encode(env)),
+        (key_fields::is_qualified(), key.is_qualified().encode(env)),
+        (
+            key_fields::owner_trust(),
+            validity::from(key.owner_trust()).encode(env),
+        ),
+        (
+            key_fields::origin(),
+            key_origin::from(key.origin()).encode(env),
+        ),
+        (
+            key_fields::last_update(),
+            key.last_update()
+                .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                .map(|t| t.as_secs().encode(env))
+                .unwrap_or(atoms::unknown().encode(env)),
+        ),
+        (
+            key_fields::protocol(),
+            crate::protocol::as_term(key.protocol()).encode(env),
+        ),
+        (key_fields::issuer_serial(), optional(key.issuer_serial())),
+        (key_fields::issuer_name(), optional(key.issuer_name())),
+        (key_fields::chain_id(), optional(key.chain_id())),
+        // For X.509 certificates the primary key is the only key, so its
+        // smartcard details describe the whole certificate.
+        (
+            key_fields::card_key(),
+            primary
+                .as_ref()
+                .map_or(false, |k| k.is_card_key())
+                .encode(env),
+        ),
+        (
+            key_fields::card_serial_number(),
+            primary
+                .as_ref()
+                .and_then(|k| k.card_serial_number().ok())
+                .map(|s| s.encode(env))
+                .unwrap_or(atoms::none().encode(env)),
+        ),
+        (
+            key_fields::user_ids(),
+            user_ids_as_list(env, key)?.encode(env),
+        ),
+        (
+            key_fields::subkeys(),
+            subkeys_as_list(env, key)?.encode(env),
+        ),
+    ];
+
+    let mut map = Term::map_new(env);
+    for (field, value) in fields {
+        map = map.map_put(field.encode(env), value)?;
+    }
+
+    Ok((atoms::ok(), map).encode(env))
+}
@@ -0,0 +1,132 @@
+use atoms;
+use context::{self, GpgmeContext};
+use error;
+use gpgme::{Context, KeyListMode};
+use key::{key_list_mode, GpgmeKey};
+use rustler::resource::ResourceArc;
+use rustler::{Encoder, Env, NifResult, Term};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+rustler_atoms! {
+    atom done;
+}
+
+enum Listing {
+    Key(GpgmeKey),
+    Done(Result<bool, gpgme::Error>),
+}
+
+/// A key listing running on its own thread and context. Keys are handed
+/// over through a bounded channel, so the listing only runs ahead of the
+/// consumer by one batch and the caller's context stays unlocked.
+pub(crate) struct GpgmeKeyStream {
+    keys: Mutex<Option<Receiver<Listing>>>,
+}
+
+fn list(
+    mut context: Context,
+    patterns: Vec<String>,
+    secret_only: bool,
+    keys: &SyncSender<Listing>,
+) -> Result<bool, gpgme::Error> {
+    let mut listing = if secret_only {
+        context.find_secret_keys(patterns)?
+    } else {
+        context.find_keys(patterns)?
+    };
+    for key in listing.by_ref() {
+        if keys.send(Listing::Key(GpgmeKey(key?))).is_err() {
+            // The stream was closed early.
+            return Ok(false);
+        }
+    }
+    Ok(listing.finish()?.is_truncated())
+}
+
+fn spawn(
+    context: &Context,
+    patterns: Vec<String>,
+    secret_only: bool,
+    mode: KeyListMode,
+    batch_size: usize,
+) -> Result<GpgmeKeyStream, gpgme::Error> {
+    let mut context = context::duplicate(context)?;
+    context.set_key_list_mode(mode)?;
+    // `GpgmeContext` is what may be moved across threads.
+    let context: GpgmeContext = context.into();
+
+    let (tx, rx) = sync_channel(batch_size);
+    thread::spawn(move || {
+        let result = match context.0.into_inner() {
+            Ok(context) => list(context, patterns, secret_only, &tx),
+            Err(_) => Err(gpgme::Error::GENERAL),
+        };
+        let _ = tx.send(Listing::Done(result));
+    });
+
+    Ok(GpgmeKeyStream {
+        keys: Mutex::new(Some(rx)),
+    })
+}
+
+/// Start listing the keys matching `patterns`, taking the same arguments
+/// as `key_list/4` plus the batch size.
+pub fn start<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let patterns: Vec<String> = args[1].decode()?;
+    let secret_only: bool = args[2].decode()?;
+    let mode = match key_list_mode::from_term(args[3]) {
+        Ok(mode) => mode,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let batch_size: usize = args[4].decode()?;
+    let context = error::lock(&res.0)?;
+
+    match spawn(&context, patterns, secret_only, mode, batch_size.max(1)) {
+        Ok(stream) => Ok((atoms::ok(), ResourceArc::new(stream)).encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Fetch up to `count` keys. Returns `{:ok, keys}` while the listing goes
+/// on and `{:done, keys, truncated}` once it has finished.
+pub fn next<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let stream: ResourceArc<GpgmeKeyStream> = args[0].decode()?;
+    let count: usize = args[1].decode()?;
+    let mut keys = error::lock(&stream.keys)?;
+
+    let mut batch = vec![];
+    let finished = match *keys {
+        Some(ref receiver) => loop {
+            if batch.len() >= count {
+                break None;
+            }
+            match receiver.recv() {
+                Ok(Listing::Key(key)) => batch.push(ResourceArc::new(key)),
+                Ok(Listing::Done(result)) => break Some(result),
+                Err(_) => break Some(Ok(false)),
+            }
+        },
+        None => Some(Ok(false)),
+    };
+
+    match finished {
+        None => Ok((atoms::ok(), batch).encode(env)),
+        Some(result) => {
+            keys.take();
+            match result {
+                Ok(truncated) => Ok((done(), batch, truncated).encode(env)),
+                Err(err) => error::as_term(env, err),
+            }
+        }
+    }
+}
+
+/// Stop the listing, e.g. when the consumer halts early.
+pub fn close<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let stream: ResourceArc<GpgmeKeyStream> = args[0].decode()?;
+    error::lock(&stream.keys)?.take();
+    Ok(atoms::ok().encode(env))
+}
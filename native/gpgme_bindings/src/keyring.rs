@@ -0,0 +1,599 @@
+use atoms;
+use binary;
+use context::{self, GpgmeContext};
+use error::{self, ArgError};
+use gpgme::{Context, Key, KeyGenerationResult, KeyListMode};
+use interact::Script;
+use key::{tofu_policy, GpgmeKey};
+use packet;
+use passphrase::Passphrase;
+use rustler::resource::ResourceArc;
+use rustler::{Encoder, Env, NifResult, Term};
+
+mod create_key_flags {
+    use error::{ArgError, ArgResult};
+    use gpgme::CreateKeyFlags;
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom sign;
+        atom encrypt;
+        atom cert;
+        atom auth;
+        atom no_password;
+        atom force;
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<CreateKeyFlags> {
+        let flags: Vec<types::atom::Atom> = term.decode()?;
+        flags.iter().fold(Ok(CreateKeyFlags::empty()), |acc, flag| {
+            let flag = match *flag {
+                f if f == sign() => CreateKeyFlags::SIGN,
+                f if f == encrypt() => CreateKeyFlags::ENCR,
+                f if f == cert() => CreateKeyFlags::CERT,
+                f if f == auth() => CreateKeyFlags::AUTH,
+                f if f == no_password() => CreateKeyFlags::NOPASSWD,
+                f if f == force() => CreateKeyFlags::FORCE,
+                _ => return Err(ArgError::Invalid("unsupported create key flag")),
+            };
+            acc.map(|a| a | flag)
+        })
+    }
+}
+
+mod expires {
+    use error::{ArgError, ArgResult};
+    use gpgme::{CreateKeyFlags, KeySigningFlags};
+    use rustler::types::atom::Atom;
+    use rustler::Term;
+    use std::time::Duration;
+
+    rustler_atoms! {
+        atom never;
+    }
+
+    /// Decode an expiration given in seconds, `0` for the engine's
+    /// default, or `:never`, which `None` stands for.
+    pub fn decode<'a>(term: Term<'a>) -> ArgResult<Option<Duration>> {
+        if let Ok(seconds) = term.decode::<u64>() {
+            return Ok(Some(Duration::from_secs(seconds)));
+        }
+        match term.decode::<Atom>() {
+            Ok(atom) if atom == never() => Ok(None),
+            _ => Err(ArgError::Invalid("unsupported expiration")),
+        }
+    }
+
+    pub fn for_key<'a>(term: Term<'a>) -> ArgResult<(Duration, CreateKeyFlags)> {
+        Ok(match decode(term)? {
+            Some(expires) => (expires, CreateKeyFlags::empty()),
+            None => (Duration::from_secs(0), CreateKeyFlags::NOEXPIRE),
+        })
+    }
+
+    /// Changing the expiration takes `0` to mean `:never` rather than a
+    /// flag, so an explicit `0` is rejected.
+    pub fn for_change<'a>(term: Term<'a>) -> ArgResult<Duration> {
+        match decode(term)? {
+            Some(expires) if expires.as_secs() == 0 => {
+                Err(ArgError::Invalid("expiration must be positive or :never"))
+            }
+            Some(expires) => Ok(expires),
+            None => Ok(Duration::from_secs(0)),
+        }
+    }
+
+    pub fn for_signature<'a>(term: Term<'a>) -> ArgResult<(Duration, KeySigningFlags)> {
+        Ok(match decode(term)? {
+            Some(expires) => (expires, KeySigningFlags::empty()),
+            None => (Duration::from_secs(0), KeySigningFlags::NOEXPIRE),
+        })
+    }
+}
+
+mod export_mode {
+    use error::{ArgError, ArgResult};
+    use gpgme::ExportMode;
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom minimal;
+        atom secret;
+        atom extern_ = "extern";
+        atom ssh;
+        atom pkcs12;
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<ExportMode> {
+        let modes: Vec<types::atom::Atom> = term.decode()?;
+        modes.iter().fold(Ok(ExportMode::empty()), |acc, mode| {
+            let mode = match *mode {
+                m if m == minimal() => ExportMode::MINIMAL,
+                m if m == secret() => ExportMode::SECRET,
+                m if m == extern_() => ExportMode::EXTERN,
+                m if m == ssh() => ExportMode::SSH,
+                m if m == pkcs12() => ExportMode::PKCS12,
+                _ => return Err(ArgError::Invalid("unsupported export mode")),
+            };
+            acc.map(|a| a | mode)
+        })
+    }
+}
+
+mod export_patterns {
+    use error::{ArgError, ArgResult};
+    use rustler::types::atom::Atom;
+    use rustler::Term;
+
+    rustler_atoms! {
+        atom all;
+    }
+
+    /// Decode the keys to export, `:all` for every key. An empty list is
+    /// rejected rather than taken to mean every key.
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<Vec<String>> {
+        if let Ok(atom) = term.decode::<Atom>() {
+            return if atom == all() {
+                Ok(Vec::new())
+            } else {
+                Err(ArgError::Invalid("unsupported export patterns"))
+            };
+        }
+        let patterns: Vec<String> = term.decode()?;
+        if patterns.is_empty() {
+            return Err(ArgError::Invalid("no keys to export"));
+        }
+        Ok(patterns)
+    }
+}
+
+/// Look up the key a generation result refers to and return it as
+/// `{:ok, fingerprint, key}`.
+fn generated_key<'a>(
+    env: Env<'a>,
+    context: &mut gpgme::Context,
+    result: KeyGenerationResult,
+    primary: Option<String>,
+) -> NifResult<Term<'a>> {
+    let fingerprint = match result.fingerprint() {
+        Ok(fingerprint) => fingerprint.to_owned(),
+        Err(_) => return error::as_term(env, gpgme::Error::GENERAL),
+    };
+    let lookup = primary.unwrap_or_else(|| fingerprint.clone());
+    match context.get_key(lookup) {
+        Ok(key) => {
+            let wrapped: ResourceArc<GpgmeKey> = ResourceArc::new(key.into());
+            Ok((atoms::ok(), fingerprint, wrapped).encode(env))
+        }
+        Err(err) => error::as_term(env, error::not_found_on_eof(err)),
+    }
+}
+
+/// Generate a new primary key for `user_id`. The passphrase protects the
+/// new secret key unless the `no_password` flag is given.
+pub fn generate<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let user_id: String = args[2].decode()?;
+    let algorithm: String = args[3].decode()?;
+    let (expires, expire_flags) = match expires::for_key(args[4]) {
+        Ok(expires) => expires,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let flags = match create_key_flags::from_term(args[5]) {
+        Ok(flags) => flags | expire_flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.create_key_with_flags(user_id, algorithm, expires, flags)
+    });
+
+    match result {
+        Ok(result) => generated_key(env, &mut context, result, None),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Add a subkey to an existing key. Returns the fingerprint reported by
+/// gpgme together with the reloaded primary key.
+pub fn add_subkey<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[2].decode()?;
+    let algorithm: String = args[3].decode()?;
+    let (expires, expire_flags) = match expires::for_key(args[4]) {
+        Ok(expires) => expires,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let flags = match create_key_flags::from_term(args[5]) {
+        Ok(flags) => flags | expire_flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+
+    let primary = match key.0.fingerprint() {
+        Ok(fingerprint) => fingerprint.to_owned(),
+        Err(_) => return error::invalid_argument(env, ArgError::Invalid("key has no fingerprint")),
+    };
+
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.create_subkey_with_flags(&key.0, algorithm, expires, flags)
+    });
+
+    match result {
+        Ok(result) => generated_key(env, &mut context, result, Some(primary)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Export the keys matching the given patterns, or every key for `:all`.
+/// The output follows the context's armor setting. With the
+/// `extern` mode the keys are sent to the configured keyserver instead
+/// and the returned data is empty.
+pub fn export<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let patterns = match export_patterns::from_term(args[2]) {
+        Ok(patterns) => patterns,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mode = match export_mode::from_term(args[3]) {
+        Ok(mode) => mode,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+
+    let mut exported = Vec::new();
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.export(patterns, mode, &mut exported)
+    });
+
+    match result {
+        Ok(()) => Ok((atoms::ok(), binary::to_term(env, &exported)?).encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+mod delete_flags {
+    use error::{ArgError, ArgResult};
+    use gpgme::DeleteKeyFlags;
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom allow_secret;
+        atom force;
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<DeleteKeyFlags> {
+        let flags: Vec<types::atom::Atom> = term.decode()?;
+        flags.iter().fold(Ok(DeleteKeyFlags::empty()), |acc, flag| {
+            let flag = match *flag {
+                f if f == allow_secret() => DeleteKeyFlags::ALLOW_SECRET,
+                f if f == force() => DeleteKeyFlags::FORCE,
+                _ => return Err(ArgError::Invalid("unsupported delete flag")),
+            };
+            acc.map(|a| a | flag)
+        })
+    }
+}
+
+/// Delete the key with the given fingerprint from the keyring. gpgme
+/// refuses with a conflict if a secret key exists and `allow_secret` is
+/// not given.
+pub fn delete<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let fingerprint: String = args[1].decode()?;
+    let flags = match delete_flags::from_term(args[2]) {
+        Ok(flags) => flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+
+    let result = context
+        .get_key(fingerprint)
+        .map_err(error::not_found_on_eof)
+        .and_then(|key| context.delete_key_with_flags(&key, flags));
+
+    match result {
+        Ok(()) => Ok(atoms::ok().encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+mod key_signing_flags {
+    use error::{ArgError, ArgResult};
+    use gpgme::KeySigningFlags;
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom local;
+        atom force;
+    }
+
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<KeySigningFlags> {
+        let flags: Vec<types::atom::Atom> = term.decode()?;
+        flags
+            .iter()
+            .fold(Ok(KeySigningFlags::empty()), |acc, flag| {
+                let flag = match *flag {
+                    f if f == local() => KeySigningFlags::LOCAL,
+                    f if f == force() => KeySigningFlags::FORCE,
+                    _ => return Err(ArgError::Invalid("unsupported key signing flag")),
+                };
+                acc.map(|a| a | flag)
+            })
+    }
+}
+
+/// Fetch a fresh copy of a key after it was changed, including the
+/// signatures and TOFU information on its user ids.
+fn reload(context: &mut Context, key: &Key) -> Result<Key, gpgme::Error> {
+    let fingerprint = key.fingerprint().map_err(|_| gpgme::Error::INV_VALUE)?;
+    let mode = context.key_list_mode();
+    context.set_key_list_mode(
+        mode | KeyListMode::LOCAL | KeyListMode::SIGS | KeyListMode::WITH_TOFU,
+    )?;
+    let result = context.get_key(fingerprint);
+    context.set_key_list_mode(mode)?;
+    result
+}
+
+/// Cut the revocation certificate out of a revoked key's export. The
+/// output follows the context's armor setting.
+fn revocation_certificate(context: &mut Context, key: &Key) -> Result<Vec<u8>, gpgme::Error> {
+    let armor = context.armor();
+    let mut exported = Vec::new();
+    context.set_armor(false);
+    let result = context.export_keys(Some(key), gpgme::ExportMode::empty(), &mut exported);
+    context.set_armor(armor);
+    result?;
+
+    let certificate = packet::key_revocation(&exported).ok_or(gpgme::Error::NOT_FOUND)?;
+    if armor {
+        Ok(packet::armor_revocation(certificate))
+    } else {
+        Ok(certificate.to_vec())
+    }
+}
+
+fn updated_key<'a>(env: Env<'a>, result: Result<Key, gpgme::Error>) -> NifResult<Term<'a>> {
+    match result {
+        Ok(key) => {
+            let wrapped: ResourceArc<GpgmeKey> = ResourceArc::new(key.into());
+            Ok((atoms::ok(), wrapped).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Certify the given user ids of a key, or all of them if the list is
+/// empty, with the context's signers.
+pub fn sign_key<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[2].decode()?;
+    let user_ids: Vec<String> = args[3].decode()?;
+    let (expires, expire_flags) = match expires::for_signature(args[4]) {
+        Ok(expires) => expires,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let flags = match key_signing_flags::from_term(args[5]) {
+        Ok(flags) => flags | expire_flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.sign_key_with_flags(&key.0, &user_ids, expires, flags)
+    })
+    .and_then(|_| reload(&mut context, &key.0));
+
+    updated_key(env, result)
+}
+
+/// Revoke the certifications `signer` made on the given user ids of a
+/// key, or on all of them if the list is empty.
+pub fn revoke_signature<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[2].decode()?;
+    let signer: ResourceArc<GpgmeKey> = args[3].decode()?;
+    let user_ids: Vec<String> = args[4].decode()?;
+    let mut context = error::lock(&res.0)?;
+
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.revoke_signature(&key.0, &signer.0, &user_ids)
+    })
+    .and_then(|_| reload(&mut context, &key.0));
+
+    updated_key(env, result)
+}
+
+/// Change the expiration of the primary key, or of the subkeys with the
+/// given fingerprints. `:never` removes the expiration.
+pub fn set_expire<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[2].decode()?;
+    let expires = match expires::for_change(args[3]) {
+        Ok(expires) => expires,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let subkeys: Vec<String> = args[4].decode()?;
+    let mut context = error::lock(&res.0)?;
+
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.set_expire(&key.0, expires, &subkeys)
+    })
+    .and_then(|_| reload(&mut context, &key.0));
+
+    updated_key(env, result)
+}
+
+/// Run one of gpgme's user id operations on a key and return the
+/// reloaded key.
+fn edit_user_id<'a, F>(env: Env<'a>, args: &[Term<'a>], f: F) -> NifResult<Term<'a>>
+where
+    F: FnOnce(&mut Context, &Key, &str) -> Result<(), gpgme::Error>,
+{
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[2].decode()?;
+    let user_id: String = args[3].decode()?;
+    let mut context = error::lock(&res.0)?;
+
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        f(ctx, &key.0, &user_id)
+    })
+    .and_then(|_| reload(&mut context, &key.0));
+
+    updated_key(env, result)
+}
+
+pub fn add_uid<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    edit_user_id(env, args, |ctx, key, user_id| ctx.add_uid(key, user_id))
+}
+
+pub fn revoke_uid<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    edit_user_id(env, args, |ctx, key, user_id| ctx.revoke_uid(key, user_id))
+}
+
+pub fn set_primary_uid<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    edit_user_id(env, args, |ctx, key, user_id| {
+        ctx.set_uid_flag(key, user_id, "primary", None::<&str>)
+    })
+}
+
+mod revocation_reason {
+    use error::{ArgError, ArgResult};
+    use rustler::{types, Term};
+
+    rustler_atoms! {
+        atom no_reason;
+        atom compromised;
+        atom superseded;
+        atom no_longer_used;
+    }
+
+    /// The answer gpg expects for `ask_revocation_reason.code`.
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<&'static str> {
+        let reason: types::atom::Atom = term.decode()?;
+        match reason {
+            r if r == no_reason() => Ok("0"),
+            r if r == compromised() => Ok("1"),
+            r if r == superseded() => Ok("2"),
+            r if r == no_longer_used() => Ok("3"),
+            _ => Err(ArgError::Invalid("unsupported revocation reason")),
+        }
+    }
+}
+
+/// Revoke a primary key. gpgme has no direct call for this, so gpg's key
+/// editing is scripted instead. Returns the revocation certificate,
+/// which revokes the key wherever it is imported, along with the reloaded
+/// key.
+pub fn revoke_key<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[2].decode()?;
+    let reason = match revocation_reason::from_term(args[3]) {
+        Ok(reason) => reason,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let description: String = args[4].decode()?;
+    let mut context = error::lock(&res.0)?;
+
+    let script = description
+        .lines()
+        .filter(|line| !line.is_empty())
+        .fold(
+            Script::new()
+                .answer("keyedit.prompt", "revkey")
+                .confirm("keyedit.revoke.subkey.okay")
+                .answer("ask_revocation_reason.code", reason)
+                .confirm("ask_revocation_reason.okay"),
+            |script, line| script.answer("ask_revocation_reason.text", line),
+        )
+        .answer("ask_revocation_reason.text", "");
+
+    let result = context::with_passphrase(&res, &mut context, &passphrase, |ctx| {
+        ctx.interact(&key.0, script, &mut Vec::<u8>::new())
+    })
+    .and_then(|_| reload(&mut context, &key.0))
+    .and_then(|key| revocation_certificate(&mut context, &key).map(|cert| (cert, key)));
+
+    match result {
+        Ok((certificate, key)) => {
+            let certificate = binary::to_term(env, &certificate)?;
+            let wrapped: ResourceArc<GpgmeKey> = ResourceArc::new(key.into());
+            Ok((atoms::ok(), certificate, wrapped).encode(env))
+        }
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+mod owner_trust {
+    use error::{ArgError, ArgResult};
+    use key::validity;
+    use rustler::{types, Term};
+
+    /// The answer gpg expects for `edit_ownertrust.value`. gpg cannot
+    /// reset a key to `unknown`, i.e. no owner trust assigned at all.
+    pub fn from_term<'a>(term: Term<'a>) -> ArgResult<&'static str> {
+        let trust: types::atom::Atom = term.decode()?;
+        match trust {
+            t if t == validity::unknown() => {
+                Err(ArgError::Invalid("use :undefined to unset owner trust"))
+            }
+            t if t == validity::undefined() => Ok("1"),
+            t if t == validity::never() => Ok("2"),
+            t if t == validity::marginal() => Ok("3"),
+            t if t == validity::full() => Ok("4"),
+            t if t == validity::ultimate() => Ok("5"),
+            _ => Err(ArgError::Invalid("unsupported owner trust")),
+        }
+    }
+}
+
+/// Set how far the owner of a key is trusted to certify other keys.
+/// gpgme has no direct call for this, so gpg's key editing is scripted.
+pub fn set_owner_trust<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[1].decode()?;
+    let trust = match owner_trust::from_term(args[2]) {
+        Ok(trust) => trust,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+
+    let script = Script::new()
+        .answer("keyedit.prompt", "trust")
+        .answer("edit_ownertrust.value", trust)
+        .confirm("edit_ownertrust.set_ultimate.okay");
+
+    let result = context
+        .interact(&key.0, script, &mut Vec::<u8>::new())
+        .and_then(|_| reload(&mut context, &key.0));
+
+    updated_key(env, result)
+}
+
+/// Set the TOFU policy of a key, e.g. to mark a compromised identity as
+/// `bad`.
+pub fn set_tofu_policy<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let key: ResourceArc<GpgmeKey> = args[1].decode()?;
+    let policy = match tofu_policy::from_term(args[2]) {
+        Ok(policy) => policy,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+    let mut context = error::lock(&res.0)?;
+
+    let result = context
+        .set_tofu_policy(&key.0, policy)
+        .and_then(|_| reload(&mut context, &key.0));
+
+    updated_key(env, result)
+}
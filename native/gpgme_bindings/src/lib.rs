@@ -7,17 +7,37 @@ extern crate lazy_static;
 extern crate gpgme;
 
 mod atoms;
+mod binary;
 mod context;
+mod error;
+mod file;
+mod interact;
 mod key;
+mod key_stream;
+mod keyring;
+mod packet;
+mod passphrase;
 mod protocol;
+mod stream;
 
 use context::GpgmeContext;
 use key::GpgmeKey;
+use key_stream::GpgmeKeyStream;
+use passphrase::GpgmeReply;
+use rustler::schedule::SchedulerFlags::DirtyIo;
 use rustler::{Env, Term};
+use stream::GpgmeStream;
 
 rustler_export_nifs! {
     "Elixir.ExGpgme.Native",
-    [("key_list", 1, key::list),
+    // Everything that talks to gpg or gpg-agent may block for a long
+    // time and therefore runs on the dirty I/O schedulers.
+    [("key_list", 1, key::list, DirtyIo),
+     ("key_list", 4, key::list_matching, DirtyIo),
+     ("key_locate", 3, key::locate, DirtyIo),
+     ("key_stream", 5, key_stream::start, DirtyIo),
+     ("key_stream_next", 2, key_stream::next, DirtyIo),
+     ("key_stream_close", 1, key_stream::close),
      ("key_id", 1, key::key_id),
      ("key_user_ids", 1, key::key_user_ids),
      ("key_subkeys", 1, key::key_subkeys),
@@ -32,6 +52,8 @@ rustler_export_nifs! {
      ("key_is_disabled" , 1, key::key_is_disabled) ,
      ("key_is_invalid"  , 1, key::key_is_invalid)  ,
      ("key_is_qualified", 1, key::key_is_qualified),
+     ("key_owner_trust", 1, key::key_owner_trust),
+     ("key_to_map", 1, key::key_to_map),
      // ("context_signers", 1, context::signers),
      ("context_signature_notations", 1, context::signature_notations),
      ("context_add_signature_notation", 4, context::add_signature_notation),
@@ -43,12 +65,48 @@ rustler_export_nifs! {
      ("context_add_signer", 2, context::add_signer),
      ("context_clear_signers", 1, context::clear_signers),
      ("context_create", 2, context::create),
-     ("context_import", 2, context::import),
-     ("context_encrypt", 3, context::encrypt),
-     ("context_encrypt_symmetric", 3, context::encrypt_symmetric),
-     ("context_decrypt", 3, context::decrypt),
-     ("context_find_key", 2, context::find_key),
-     ("context_info", 1, context::info)],
+     ("context_create", 3, context::create_with_options),
+     ("context_options", 1, context::options),
+     ("context_set_option", 3, context::set_option),
+     ("context_import", 2, context::import, DirtyIo),
+     ("context_import_pkcs12", 3, context::import_pkcs12, DirtyIo),
+     ("context_encrypt", 5, context::encrypt, DirtyIo),
+     ("context_encrypt_symmetric", 3, context::encrypt_symmetric, DirtyIo),
+     ("context_decrypt", 3, context::decrypt, DirtyIo),
+     ("context_sign", 4, context::sign, DirtyIo),
+     ("context_verify", 2, context::verify, DirtyIo),
+     ("context_verify_detached", 3, context::verify_detached, DirtyIo),
+     ("context_encrypt_sign", 5, context::encrypt_sign, DirtyIo),
+     ("context_decrypt_verify", 3, context::decrypt_verify, DirtyIo),
+     ("context_encrypt_file", 6, file::encrypt, DirtyIo),
+     ("context_decrypt_file", 4, file::decrypt, DirtyIo),
+     ("context_sign_file", 5, file::sign, DirtyIo),
+     ("context_verify_file", 4, file::verify, DirtyIo),
+     ("context_find_key", 2, context::find_key, DirtyIo),
+     ("context_generate_key", 6, keyring::generate, DirtyIo),
+     ("context_add_subkey", 6, keyring::add_subkey, DirtyIo),
+     ("context_export", 4, keyring::export, DirtyIo),
+     ("context_delete_key", 3, keyring::delete, DirtyIo),
+     ("context_sign_key", 6, keyring::sign_key, DirtyIo),
+     ("context_revoke_signature", 5, keyring::revoke_signature, DirtyIo),
+     ("context_set_expire", 5, keyring::set_expire, DirtyIo),
+     ("context_add_uid", 4, keyring::add_uid, DirtyIo),
+     ("context_revoke_uid", 4, keyring::revoke_uid, DirtyIo),
+     ("context_set_primary_uid", 4, keyring::set_primary_uid, DirtyIo),
+     ("context_revoke_key", 5, keyring::revoke_key, DirtyIo),
+     ("context_set_owner_trust", 3, keyring::set_owner_trust, DirtyIo),
+     ("context_set_tofu_policy", 3, keyring::set_tofu_policy, DirtyIo),
+     ("context_set_passphrase_provider", 3, passphrase::set_provider),
+     ("context_clear_passphrase_provider", 1, passphrase::clear_provider),
+     ("passphrase_reply", 2, passphrase::reply),
+     ("context_info", 1, context::info),
+     ("context_armor", 1, context::armor),
+     ("context_set_armor", 2, context::set_armor),
+     ("stream_encrypt", 4, stream::encrypt, DirtyIo),
+     ("stream_decrypt", 2, stream::decrypt, DirtyIo),
+     ("stream_write", 2, stream::write, DirtyIo),
+     ("stream_finish", 1, stream::finish, DirtyIo),
+     ("stream_close", 1, stream::close, DirtyIo)],
     Some(on_load)
 }
 
@@ -56,5 +114,8 @@ pub fn on_load<'a>(env: Env<'a>, args: Term<'a>) -> bool {
     println!("Loading ex_gpgme with args: {:?}", args);
     resource_struct_init!(GpgmeContext, env);
     resource_struct_init!(GpgmeKey, env);
+    resource_struct_init!(GpgmeStream, env);
+    resource_struct_init!(GpgmeKeyStream, env);
+    resource_struct_init!(GpgmeReply, env);
     true
 }
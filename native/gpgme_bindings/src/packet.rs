@@ -0,0 +1,298 @@
+const SIGNATURE: u8 = 2;
+const PUBLIC_KEY: u8 = 6;
+const KEY_REVOCATION: u8 = 0x20;
+
+struct Packet<'a> {
+    tag: u8,
+    body: &'a [u8],
+    /// The whole packet, header included.
+    raw: &'a [u8],
+}
+
+fn be_length(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |len, &b| (len << 8) | b as usize)
+}
+
+/// Split the first packet off `data`. Returns `None` for malformed
+/// packets and for partial or indeterminate lengths, which exported keys
+/// do not use.
+fn next<'a>(data: &'a [u8]) -> Option<(Packet<'a>, &'a [u8])> {
+    let header = *data.first()?;
+    if header & 0x80 == 0 {
+        return None;
+    }
+    let (tag, header_len, body_len): (u8, usize, usize) = if header & 0x40 != 0 {
+        let tag = header & 0x3f;
+        match *data.get(1)? as usize {
+            first @ 0..=191 => (tag, 2, first),
+            first @ 192..=223 => (tag, 3, ((first - 192) << 8) + *data.get(2)? as usize + 192),
+            255 => (tag, 6, be_length(data.get(2..6)?)),
+            _ => return None,
+        }
+    } else {
+        let tag = (header >> 2) & 0x0f;
+        match header & 0x03 {
+            0 => (tag, 2, be_length(data.get(1..2)?)),
+            1 => (tag, 3, be_length(data.get(1..3)?)),
+            2 => (tag, 5, be_length(data.get(1..5)?)),
+            _ => return None,
+        }
+    };
+    let end = header_len.checked_add(body_len)?;
+    let raw = data.get(..end)?;
+    let packet = Packet {
+        tag,
+        body: &raw[header_len..],
+        raw,
+    };
+    Some((packet, &data[end..]))
+}
+
+fn signature_type(body: &[u8]) -> Option<u8> {
+    match *body.first()? {
+        3 => body.get(2).cloned(),
+        _ => body.get(1).cloned(),
+    }
+}
+
+/// The key revocation signature among the signatures directly on the
+/// primary key of an unarmored, exported key. On its own it is what gpg
+/// calls a revocation certificate, which gpgme has no call to create.
+/// Only as much of the OpenPGP packet format (RFC 4880) as needed for
+/// this is understood.
+pub fn key_revocation(key: &[u8]) -> Option<&[u8]> {
+    let (primary, mut rest) = next(key)?;
+    if primary.tag != PUBLIC_KEY {
+        return None;
+    }
+    while let Some((packet, remaining)) = next(rest) {
+        if packet.tag != SIGNATURE {
+            break;
+        }
+        if signature_type(packet.body) == Some(KEY_REVOCATION) {
+            return Some(packet.raw);
+        }
+        rest = remaining;
+    }
+    None
+}
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = be_length(&bytes);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - 6 * i)) & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn crc24(data: &[u8]) -> u32 {
+    data.iter().fold(0x00b7_04ce, |crc, &byte| {
+        (0..8).fold(crc ^ (u32::from(byte) << 16), |crc, _| {
+            let crc = crc << 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^ 0x0186_4cfb
+            } else {
+                crc
+            }
+        })
+    }) & 0x00ff_ffff
+}
+
+/// ASCII armor a revocation certificate the way `gpg --gen-revoke` does.
+pub fn armor_revocation(certificate: &[u8]) -> Vec<u8> {
+    let crc = crc24(certificate);
+    let mut armored = String::from(
+        "-----BEGIN PGP PUBLIC KEY BLOCK-----\nComment: This is a revocation certificate\n\n",
+    );
+    let encoded = base64(certificate);
+    for line in encoded.as_bytes().chunks(64) {
+        armored.push_str(&String::from_utf8_lossy(line));
+        armored.push('\n');
+    }
+    armored.push('=');
+    armored.push_str(&base64(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]));
+    armored.push_str("\n-----END PGP PUBLIC KEY BLOCK-----\n");
+    armored.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A version 4 signature packet body of the given type, padded with
+    /// junk the parser does not look at.
+    fn signature(sigclass: u8) -> Vec<u8> {
+        vec![4, sigclass, 1, 8, 0, 0, 0, 0]
+    }
+
+    fn new_format(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0xc0 | tag, body.len() as u8];
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    #[test]
+    fn new_format_lengths() {
+        let body = vec![7; 1000];
+
+        let one = new_format(SIGNATURE, &body[..191]);
+        let (packet, rest) = next(&one).unwrap();
+        assert_eq!(
+            (packet.tag, packet.body.len(), rest.len()),
+            (SIGNATURE, 191, 0)
+        );
+
+        let mut two = vec![0xc2, 0xc2, 0xf8];
+        two.extend_from_slice(&body);
+        let (packet, _) = next(&two).unwrap();
+        assert_eq!(packet.body.len(), (0xc2 - 192) * 256 + 0xf8 + 192);
+
+        let mut five = vec![0xc2, 0xff, 0, 0, 0x03, 0xe8];
+        five.extend_from_slice(&body);
+        five.push(0);
+        let (packet, rest) = next(&five).unwrap();
+        assert_eq!(
+            (packet.body.len(), rest, packet.raw.len()),
+            (1000, &[0][..], 1006)
+        );
+    }
+
+    #[test]
+    fn old_format_lengths() {
+        let (packet, _) = next(&[0x88, 2, 1, 2]).unwrap();
+        assert_eq!((packet.tag, packet.body), (SIGNATURE, &[1, 2][..]));
+        let (packet, _) = next(&[0x89, 0, 2, 1, 2]).unwrap();
+        assert_eq!(packet.body, &[1, 2][..]);
+        let (packet, _) = next(&[0x8a, 0, 0, 0, 2, 1, 2]).unwrap();
+        assert_eq!(packet.body, &[1, 2][..]);
+    }
+
+    #[test]
+    fn rejects_unsupported_lengths() {
+        // Indeterminate old-format length.
+        assert!(next(&[0x8b, 1, 2]).is_none());
+        // Partial new-format lengths.
+        for first in 224..255 {
+            assert!(next(&[0xc2, first, 1, 2]).is_none());
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_packets() {
+        assert!(next(&[]).is_none());
+        // Not a packet tag.
+        assert!(next(&[0x42, 1, 0]).is_none());
+        // Truncated headers.
+        assert!(next(&[0xc2]).is_none());
+        assert!(next(&[0xc2, 0xc0]).is_none());
+        assert!(next(&[0xc2, 0xff, 0, 0]).is_none());
+        assert!(next(&[0x89, 0]).is_none());
+        assert!(next(&[0x8a, 0, 0, 0]).is_none());
+        // Bodies shorter than their length.
+        assert!(next(&[0xc2, 3, 1, 2]).is_none());
+        assert!(next(&[0x88, 3, 1, 2]).is_none());
+        assert!(next(&[0xc2, 0xff, 0xff, 0xff, 0xff, 0xff, 1]).is_none());
+        assert!(next(&[0x8a, 0xff, 0xff, 0xff, 0xff, 1]).is_none());
+    }
+
+    #[test]
+    fn finds_the_key_revocation() {
+        let revocation = new_format(SIGNATURE, &signature(KEY_REVOCATION));
+        let mut key = new_format(PUBLIC_KEY, &[4, 1, 2, 3]);
+        key.extend(new_format(SIGNATURE, &signature(0x1f)));
+        key.extend(&revocation);
+        key.extend(new_format(13, b"foo"));
+        assert_eq!(key_revocation(&key), Some(&revocation[..]));
+
+        // Version 3 signatures keep the type at another offset.
+        let v3 = new_format(SIGNATURE, &[3, 5, KEY_REVOCATION, 0, 0, 0, 0]);
+        let mut key = new_format(PUBLIC_KEY, &[4, 1, 2, 3]);
+        key.extend(&v3);
+        assert_eq!(key_revocation(&key), Some(&v3[..]));
+    }
+
+    #[test]
+    fn only_looks_at_signatures_on_the_primary_key() {
+        let mut key = new_format(PUBLIC_KEY, &[4, 1, 2, 3]);
+        key.extend(new_format(13, b"foo"));
+        key.extend(new_format(SIGNATURE, &signature(KEY_REVOCATION)));
+        assert_eq!(key_revocation(&key), None);
+
+        let mut not_a_key = new_format(13, b"foo");
+        not_a_key.extend(new_format(SIGNATURE, &signature(KEY_REVOCATION)));
+        assert_eq!(key_revocation(&not_a_key), None);
+
+        assert_eq!(key_revocation(&[]), None);
+        assert_eq!(key_revocation(&new_format(SIGNATURE, &[])), None);
+    }
+
+    #[test]
+    fn survives_truncated_and_garbled_input() {
+        let mut key = new_format(PUBLIC_KEY, &[4, 1, 2, 3]);
+        key.extend(new_format(SIGNATURE, &signature(0x1f)));
+        key.extend(new_format(SIGNATURE, &signature(KEY_REVOCATION)));
+        for end in 0..key.len() {
+            let _ = key_revocation(&key[..end]);
+        }
+        let mut seed: u32 = 1;
+        for _ in 0..10_000 {
+            let garbled: Vec<u8> = key
+                .iter()
+                .map(|&byte| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    if seed >> 28 == 0 {
+                        (seed >> 16) as u8
+                    } else {
+                        byte
+                    }
+                })
+                .collect();
+            let _ = key_revocation(&garbled);
+        }
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd]), "//79");
+    }
+
+    #[test]
+    fn crc24_check_value() {
+        assert_eq!(crc24(b""), 0x00b7_04ce);
+        assert_eq!(crc24(b"123456789"), 0x0021_cf02);
+    }
+
+    #[test]
+    fn armor() {
+        let armored = String::from_utf8(armor_revocation(&[0; 100])).unwrap();
+        let lines: Vec<&str> = armored.lines().collect();
+        assert_eq!(lines[0], "-----BEGIN PGP PUBLIC KEY BLOCK-----");
+        assert_eq!(lines[2], "");
+        // 100 bytes take 136 characters, wrapped at 64.
+        assert_eq!(
+            (lines[3].len(), lines[4].len(), lines[5].len()),
+            (64, 64, 8)
+        );
+        assert!(lines[6].starts_with('=') && lines[6].len() == 5);
+        assert_eq!(lines[7], "-----END PGP PUBLIC KEY BLOCK-----");
+    }
+}
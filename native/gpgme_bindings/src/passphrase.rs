@@ -0,0 +1,177 @@
+use atoms;
+use context::GpgmeContext;
+use error;
+use gpgme::PassphraseRequest;
+use rustler::env::OwnedEnv;
+use rustler::resource::ResourceArc;
+use rustler::types::atom::Atom;
+use rustler::types::binary::Binary;
+use rustler::types::pid::Pid;
+use rustler::{Decoder, Encoder, Env, NifResult, Term};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+rustler_atoms! {
+    atom gpgme_passphrase_request;
+    atom cancel;
+    atom provider;
+    atom uid_hint;
+    atom description;
+    atom prev_was_bad;
+}
+
+/// The Elixir process passphrase requests are forwarded to, and how long
+/// to wait for its answer.
+#[derive(Clone)]
+pub(crate) struct Provider {
+    pid: Pid,
+    timeout: Duration,
+}
+
+/// How an operation answers passphrase requests, decoded from either a
+/// binary or `:provider`.
+pub(crate) enum Passphrase {
+    Fixed(Vec<u8>),
+    Provider,
+}
+
+impl<'a> Decoder<'a> for Passphrase {
+    fn decode(term: Term<'a>) -> NifResult<Passphrase> {
+        match term.decode::<Atom>() {
+            Ok(atom) if atom == provider() => Ok(Passphrase::Provider),
+            Ok(_) => Err(rustler::Error::BadArg),
+            Err(_) => {
+                let passphrase: Binary = term.decode()?;
+                Ok(Passphrase::Fixed(passphrase.as_slice().to_vec()))
+            }
+        }
+    }
+}
+
+/// The channel an outstanding request is answered through. The answer is
+/// `None` when the provider cancels.
+pub(crate) struct GpgmeReply(Mutex<Option<Sender<Option<Vec<u8>>>>>);
+
+struct Request {
+    uid_hint: Option<String>,
+    description: Option<String>,
+    prev_was_bad: bool,
+}
+
+fn optional<'a>(env: Env<'a>, value: &Option<String>) -> Term<'a> {
+    match *value {
+        Some(ref value) => value.encode(env),
+        None => atoms::none().encode(env),
+    }
+}
+
+fn request_map<'a>(env: Env<'a>, request: &Request) -> NifResult<Term<'a>> {
+    Term::map_new(env)
+        .map_put(uid_hint().encode(env), optional(env, &request.uid_hint))?
+        .map_put(
+            description().encode(env),
+            optional(env, &request.description),
+        )?
+        .map_put(prev_was_bad().encode(env), request.prev_was_bad.encode(env))
+}
+
+/// Send `{:gpgme_passphrase_request, reply, request}` to the provider.
+/// `enif_send` without a calling environment is only allowed from threads
+/// the VM does not manage, so the message is sent from a helper thread.
+fn send(provider: &Provider, reply: ResourceArc<GpgmeReply>, request: Request) -> bool {
+    let pid = provider.pid.clone();
+    thread::spawn(move || {
+        let mut env = OwnedEnv::new();
+        let message = env.run(|env| {
+            request_map(env, &request)
+                .map(|map| env.save((gpgme_passphrase_request(), reply, map).encode(env)))
+        });
+        match message {
+            Ok(message) => {
+                env.send_and_clear(&pid, |env| message.load(env));
+                true
+            }
+            Err(_) => false,
+        }
+    })
+    .join()
+    .unwrap_or(false)
+}
+
+/// Ask the provider for a passphrase and block until it answers, cancels
+/// or the timeout passes.
+fn ask(provider: &Provider, request: &PassphraseRequest) -> Result<Vec<u8>, gpgme::Error> {
+    let (tx, rx): (_, Receiver<Option<Vec<u8>>>) = channel();
+    let reply = ResourceArc::new(GpgmeReply(Mutex::new(Some(tx))));
+    let request = Request {
+        uid_hint: request.user_id_hint().ok().map(|s| s.to_owned()),
+        description: request.description().ok().map(|s| s.to_owned()),
+        prev_was_bad: request.prev_attempt_failed,
+    };
+    if !send(provider, reply, request) {
+        return Err(gpgme::Error::GENERAL);
+    }
+    match rx.recv_timeout(provider.timeout) {
+        Ok(Some(passphrase)) => Ok(passphrase),
+        Ok(None) => Err(gpgme::Error::CANCELED),
+        Err(_) => Err(gpgme::Error::TIMEOUT),
+    }
+}
+
+/// Answer a passphrase request, either with a fixed passphrase or with
+/// what the provider registered on the context replies. Asking for the
+/// provider without one registered fails with `:no_passphrase`.
+pub(crate) fn answer(
+    res: &GpgmeContext,
+    passphrase: &Passphrase,
+    request: &PassphraseRequest,
+) -> Result<Vec<u8>, gpgme::Error> {
+    if let Passphrase::Fixed(ref passphrase) = *passphrase {
+        return Ok(passphrase.clone());
+    }
+    let provider = res.1.lock().map_err(|_| gpgme::Error::GENERAL)?.clone();
+    match provider {
+        Some(provider) => ask(&provider, request),
+        None => Err(gpgme::Error::NO_PASSPHRASE),
+    }
+}
+
+/// Route the passphrase requests of a context to an Elixir process.
+pub fn set_provider<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let pid: Pid = args[1].decode()?;
+    let timeout: u64 = args[2].decode()?;
+    *error::lock(&res.1)? = Some(Provider {
+        pid,
+        timeout: Duration::from_millis(timeout),
+    });
+    Ok(atoms::ok().encode(env))
+}
+
+pub fn clear_provider<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    *error::lock(&res.1)? = None;
+    Ok(atoms::ok().encode(env))
+}
+
+/// Answer an outstanding request with `{:ok, passphrase}` or `:cancel`.
+/// Replying twice, or after the request timed out, is a no-op.
+pub fn reply<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let reply: ResourceArc<GpgmeReply> = args[0].decode()?;
+    let answer = match args[1].decode::<Atom>() {
+        Ok(atom) if atom == cancel() => None,
+        _ => {
+            let (ok, passphrase): (Atom, Binary) = args[1].decode()?;
+            if ok != atoms::ok() {
+                return Err(rustler::Error::BadArg);
+            }
+            Some(passphrase.as_slice().to_vec())
+        }
+    };
+    if let Some(tx) = error::lock(&reply.0)?.take() {
+        let _ = tx.send(answer);
+    }
+    Ok(atoms::ok().encode(env))
+}
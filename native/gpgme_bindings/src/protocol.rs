@@ -1,22 +1,26 @@
+use error::{ArgError, ArgResult};
 use gpgme::Protocol;
 use rustler::*;
 
 rustler_atoms! {
     atom openpgp;
+    atom cms;
     atom unsupported_protocol;
 }
 
-pub fn from_term<'a>(term: Term<'a>) -> NifResult<Protocol> {
+pub fn from_term<'a>(term: Term<'a>) -> ArgResult<Protocol> {
     let input: types::atom::Atom = term.decode()?;
     match input {
         _ if input == openpgp() => Ok(Protocol::OpenPgp),
-        _ => Err(Error::Atom("unsupported_protocol")),
+        _ if input == cms() => Ok(Protocol::Cms),
+        _ => Err(ArgError::Invalid("unsupported protocol")),
     }
 }
 
 pub fn as_term(protocol: Protocol) -> types::atom::Atom {
     match protocol {
         Protocol::OpenPgp => openpgp(),
+        Protocol::Cms => cms(),
         _ => unsupported_protocol(),
     }
 }
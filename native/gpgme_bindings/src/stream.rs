@@ -0,0 +1,272 @@
+use atoms;
+use binary;
+use context::{self, GpgmeContext};
+use error;
+use gpgme::{Context, Data};
+use passphrase::Passphrase;
+use rustler::resource::ResourceArc;
+use rustler::types::binary::Binary;
+use rustler::{Encoder, Env, NifResult, Term};
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+rustler_atoms! {
+    atom done;
+}
+
+/// How many output chunks the worker may produce before it has to wait
+/// for them to be collected.
+const OUTPUT_CHUNKS: usize = 16;
+
+/// How long to wait for output while the worker cannot take more input.
+const OUTPUT_POLL: Duration = Duration::from_millis(10);
+
+/// A gpgme operation running on its own thread and context. Input and
+/// output chunks are handed over through bounded channels, so only a few
+/// chunks are ever held in memory. The worker blocks while its output is
+/// not collected and is drained whenever it cannot take more input.
+pub(crate) struct GpgmeStream {
+    input: Mutex<Option<SyncSender<Vec<u8>>>>,
+    output: Mutex<Option<Receiver<Vec<u8>>>>,
+    worker: Mutex<Option<JoinHandle<Result<(), gpgme::Error>>>>,
+}
+
+struct ChannelReader {
+    chunks: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            match self.chunks.recv() {
+                Ok(chunk) => {
+                    self.buffer = chunk;
+                    self.position = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let len = std::cmp::min(buf.len(), self.buffer.len() - self.position);
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+struct ChannelWriter(SyncSender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.send(buf.to_vec()) {
+            Ok(()) => Ok(buf.len()),
+            Err(_) => Err(io::Error::new(io::ErrorKind::BrokenPipe, "stream closed")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn spawn<F>(operation: F) -> GpgmeStream
+where
+    F: FnOnce(Data<'static>, Data<'static>) -> Result<(), gpgme::Error> + Send + 'static,
+{
+    let (input_tx, input_rx) = sync_channel(1);
+    let (output_tx, output_rx) = sync_channel(OUTPUT_CHUNKS);
+
+    let worker = thread::spawn(move || {
+        let reader = ChannelReader {
+            chunks: input_rx,
+            buffer: Vec::new(),
+            position: 0,
+        };
+        let input = Data::from_reader(reader).map_err(|e| e.error())?;
+        let output = Data::from_writer(ChannelWriter(output_tx)).map_err(|e| e.error())?;
+        operation(input, output)
+    });
+
+    GpgmeStream {
+        input: Mutex::new(Some(input_tx)),
+        output: Mutex::new(Some(output_rx)),
+        worker: Mutex::new(Some(worker)),
+    }
+}
+
+/// Wait for the worker, once its output has been drained or dropped.
+fn join(stream: &GpgmeStream) -> NifResult<Result<(), gpgme::Error>> {
+    let worker = error::lock(&stream.worker)?.take();
+    Ok(match worker {
+        Some(worker) => worker.join().unwrap_or_else(|_| Err(gpgme::Error::GENERAL)),
+        None => Ok(()),
+    })
+}
+
+/// Signal the end of input and collect up to `OUTPUT_CHUNKS` chunks of
+/// output after `chunks`. Returns `{:ok, chunks}` while the worker goes
+/// on and `{:done, chunks}` once it has finished.
+fn finish_stream<'a>(
+    env: Env<'a>,
+    stream: &GpgmeStream,
+    mut chunks: Vec<Term<'a>>,
+) -> NifResult<Term<'a>> {
+    error::lock(&stream.input)?.take();
+    let finished = match *error::lock(&stream.output)? {
+        Some(ref output) => loop {
+            if chunks.len() >= OUTPUT_CHUNKS {
+                break false;
+            }
+            match output.recv() {
+                Ok(chunk) => chunks.push(binary::to_term(env, &chunk)?),
+                // The worker has dropped its end of the channel.
+                Err(_) => break true,
+            }
+        },
+        None => true,
+    };
+    if !finished {
+        return Ok((atoms::ok(), chunks).encode(env));
+    }
+
+    error::lock(&stream.output)?.take();
+    match join(stream)? {
+        Ok(()) => Ok((done(), chunks).encode(env)),
+        Err(err) => error::as_term(env, err),
+    }
+}
+
+/// Set up a context of its own for the worker, with the same settings and
+/// passphrase provider, so the caller's context is not locked for as long
+/// as the stream runs.
+fn detach(res: &GpgmeContext, context: &Context) -> NifResult<Result<GpgmeContext, gpgme::Error>> {
+    let provider = error::lock(&res.1)?.clone();
+    Ok(context::duplicate(context)
+        .map(|context| GpgmeContext(Mutex::new(context), Mutex::new(provider))))
+}
+
+/// Start encrypting for the given recipients. Plaintext is fed with
+/// `stream_write` and the operation is completed by `stream_finish`.
+pub fn encrypt<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let fingerprints: Vec<String> = args[2].decode()?;
+    let flags = match context::encrypt_flags::from_term(args[3]) {
+        Ok(flags) => flags,
+        Err(err) => return error::invalid_argument(env, err),
+    };
+
+    let (keys, worker) = {
+        let mut context = error::lock(&res.0)?;
+        let keys = match context::recipient_keys(&mut context, &fingerprints) {
+            Ok(keys) => keys,
+            Err(err) => return error::as_term(env, err),
+        };
+        match detach(&res, &context)? {
+            Ok(worker) => (keys, worker),
+            Err(err) => return error::as_term(env, err),
+        }
+    };
+
+    let stream = spawn(move |mut plaintext, mut ciphertext| {
+        let mut context = worker.0.lock().map_err(|_| gpgme::Error::GENERAL)?;
+        context::with_passphrase(&worker, &mut context, &passphrase, |ctx| {
+            ctx.encrypt_with_flags(&keys, &mut plaintext, &mut ciphertext, flags)
+        })?;
+        Ok(())
+    });
+
+    Ok((atoms::ok(), ResourceArc::new(stream)).encode(env))
+}
+
+/// Start decrypting. Ciphertext is fed with `stream_write` and the
+/// operation is completed by `stream_finish`.
+pub fn decrypt<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let res: ResourceArc<GpgmeContext> = args[0].decode()?;
+    let passphrase: Passphrase = args[1].decode()?;
+    let worker = {
+        let context = error::lock(&res.0)?;
+        match detach(&res, &context)? {
+            Ok(worker) => worker,
+            Err(err) => return error::as_term(env, err),
+        }
+    };
+
+    let stream = spawn(move |mut ciphertext, mut plaintext| {
+        let mut context = worker.0.lock().map_err(|_| gpgme::Error::GENERAL)?;
+        context::with_passphrase(&worker, &mut context, &passphrase, |ctx| {
+            ctx.decrypt(&mut ciphertext, &mut plaintext)
+        })?;
+        Ok(())
+    });
+
+    Ok((atoms::ok(), ResourceArc::new(stream)).encode(env))
+}
+
+/// Hand the next input chunk to the worker and return the output that is
+/// ready so far. While the worker cannot take more input its output is
+/// collected, as it may be waiting for exactly that. Once the worker has
+/// stopped, the rest of its output is returned as by `stream_finish`.
+pub fn write<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let stream: ResourceArc<GpgmeStream> = args[0].decode()?;
+    let chunk: Binary = args[1].decode()?;
+
+    let mut chunks = Vec::<Term<'a>>::new();
+    let sent = {
+        let input = error::lock(&stream.input)?;
+        let output = error::lock(&stream.output)?;
+        match (input.as_ref(), output.as_ref()) {
+            (Some(input), Some(output)) => {
+                let mut pending = chunk.as_slice().to_vec();
+                let sent = loop {
+                    match input.try_send(pending) {
+                        Ok(()) => break true,
+                        Err(TrySendError::Full(chunk)) => pending = chunk,
+                        Err(TrySendError::Disconnected(_)) => break false,
+                    }
+                    match output.recv_timeout(OUTPUT_POLL) {
+                        Ok(chunk) => chunks.push(binary::to_term(env, &chunk)?),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        // The worker has stopped and is dropping its input too.
+                        Err(RecvTimeoutError::Disconnected) => break false,
+                    }
+                };
+                if sent {
+                    while let Ok(chunk) = output.try_recv() {
+                        chunks.push(binary::to_term(env, &chunk)?);
+                    }
+                }
+                sent
+            }
+            _ => false,
+        }
+    };
+
+    if !sent {
+        return finish_stream(env, &stream, chunks);
+    }
+
+    Ok((atoms::ok(), chunks).encode(env))
+}
+
+/// Signal the end of input and return the next batch of the remaining
+/// output, `{:done, chunks}` once the worker has finished.
+pub fn finish<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let stream: ResourceArc<GpgmeStream> = args[0].decode()?;
+    finish_stream(env, &stream, Vec::new())
+}
+
+/// Abort a stream, e.g. when the consumer stops early. Dropping both
+/// channels makes the worker fail on its next read or write, after which
+/// it is joined.
+pub fn close<'a>(env: Env<'a>, args: &[Term<'a>]) -> NifResult<Term<'a>> {
+    let stream: ResourceArc<GpgmeStream> = args[0].decode()?;
+    error::lock(&stream.input)?.take();
+    error::lock(&stream.output)?.take();
+    join(&stream)?;
+    Ok(atoms::ok().encode(env))
+}
@@ -9,6 +9,54 @@ defmodule ExGpgme.Test do
       assert context.protocol == :openpgp
       assert is_reference(context.ref)
       assert is_binary(context.version)
+      assert context.armor == true
+    end
+
+    test "create without armor", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], armor: false)
+      assert context.armor == false
+      {:ok, context} = ExGpgme.set_armor(context, true)
+      assert context.armor == true
+    end
+
+    test "create with options", ctx do
+      {:ok, context} =
+        ExGpgme.create(
+          home: ctx[:gnupg_home],
+          text_mode: false,
+          offline: true,
+          key_list_mode: [:local, :sigs]
+        )
+
+      assert context.home == ctx[:gnupg_home]
+      assert context.text_mode == false
+      assert context.offline == true
+      assert context.pinentry_mode == :loopback
+      assert context.key_list_mode == [:local, :sigs]
+
+      {:ok, %{text_mode: false, offline: true, armor: true}} = ExGpgme.options(context)
+      [key] = ExGpgme.list_keys(context)
+      [%{signatures: [_ | _]}] = key.user_ids
+    end
+
+    test "change options", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, context} = ExGpgme.set_option(context, :pinentry_mode, :cancel)
+      assert context.pinentry_mode == :cancel
+      {:ok, %{pinentry_mode: :cancel}} = ExGpgme.options(context)
+
+      {:ok, context} = ExGpgme.set_option(context, :include_certs, 1)
+      assert context.include_certs == 1
+    end
+
+    test "rejects unknown options", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:error, %{code: :invalid_value, source: :user}} = ExGpgme.set_option(context, :bogus, true)
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.set_option(context, :pinentry_mode, :bogus)
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.create(path: ctx[:gnupg_home], pinentry_mode: :bogus)
     end
   end
 
@@ -86,6 +134,71 @@ defmodule ExGpgme.Test do
       } = key
     end
 
+    test "typed key details", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      [key] = ExGpgme.list_keys(context, mode: [:local, :sigs])
+
+      %ExGpgme.Key{owner_trust: :unknown, protocol: :openpgp, chain_id: :none} = key
+      assert is_integer(key.last_update) or key.last_update == :unknown
+
+      [%ExGpgme.Subkey{can_certify: true}, %ExGpgme.Subkey{can_encrypt: true}] = key.subkeys
+
+      [%ExGpgme.UserId{uid: "Foo McBar <foo@mcbar.dev>", signatures: [signature | _]}] =
+        key.user_ids
+
+      %ExGpgme.KeySignature{signer_key: "728052F947BD30B8"} = signature
+    end
+
+    test "list matching patterns", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, _} = ExGpgme.import_key(context, File.read!("test/data/boaty_mcboatface/public.asc"))
+
+      [%{fingerprint: "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"}] =
+        ExGpgme.list_keys(context, patterns: "@mcbar.dev")
+
+      [] = ExGpgme.list_keys(context, patterns: ["nobody@example.org"])
+      assert length(ExGpgme.list_keys(context, patterns: [])) == 2
+    end
+
+    test "list secret keys only", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, _} = ExGpgme.import_key(context, File.read!("test/data/boaty_mcboatface/public.asc"))
+
+      [%{fingerprint: "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8", has_secret: true}] =
+        ExGpgme.list_keys(context, secret_only: true)
+    end
+
+    test "list with signatures", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      [key] = ExGpgme.list_keys(context, mode: [:local, :sigs])
+      [%{signatures: [_ | _]}] = key.user_ids
+    end
+
+    test "rejects unknown listing modes", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.list_keys(context, mode: [:bogus])
+    end
+
+    test "stream in batches", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, _} = ExGpgme.import_key(context, File.read!("test/data/boaty_mcboatface/public.asc"))
+
+      keys = context |> ExGpgme.stream_keys(batch_size: 1) |> Enum.to_list()
+      assert length(keys) == 2
+
+      [%{fingerprint: "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"}] =
+        context |> ExGpgme.stream_keys(patterns: "foo@mcbar.dev") |> Enum.to_list()
+    end
+
+    test "stop streaming early", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, _} = ExGpgme.import_key(context, File.read!("test/data/boaty_mcboatface/public.asc"))
+
+      [_] = context |> ExGpgme.stream_keys(batch_size: 1) |> Enum.take(1)
+      assert length(ExGpgme.list_keys(context)) == 2
+    end
+
     test "import public key", ctx do
       {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
       keys = ExGpgme.list_keys(context)
@@ -328,7 +441,395 @@ defmodule ExGpgme.Test do
 
     test "find key should return error on bogus input", ctx do
       {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
-      {:error, :not_found} = ExGpgme.find_key(context, "Dr. Baz")
+      {:error, %{code: :not_found}} = ExGpgme.find_key(context, "Dr. Baz")
+    end
+  end
+
+  describe "Key generation" do
+    test "generate a primary key", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+
+      {:ok, fingerprint, key} =
+        ExGpgme.generate_key(context, "Tenant <tenant@mcbar.dev>",
+          algorithm: "ed25519",
+          expires: :never,
+          flags: [:sign, :cert, :no_password]
+        )
+
+      assert key.fingerprint == fingerprint
+      assert key.can_sign
+      refute key.can_encrypt
+      [%{email: "tenant@mcbar.dev"}] = key.user_ids
+      [%{expiration_time: :unknown}] = key.subkeys
+      {:ok, %{fingerprint: ^fingerprint}} = ExGpgme.find_key(context, "tenant@mcbar.dev")
+    end
+
+    test "add a subkey", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+
+      {:ok, _, key} =
+        ExGpgme.generate_key(context, "tenant@mcbar.dev",
+          algorithm: "ed25519",
+          flags: [:sign],
+          passphrase: passphrase
+        )
+
+      {:ok, _, key} =
+        ExGpgme.add_subkey(context, key, "cv25519",
+          expires: 86_400,
+          flags: [:encrypt],
+          passphrase: passphrase
+        )
+
+      [_primary, subkey] = key.subkeys
+      assert subkey.can_encrypt
+      assert subkey.expiration_time - subkey.creation_time == 86_400
+    end
+
+    test "rejects unknown algorithms", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+
+      {:error, _} =
+        ExGpgme.generate_key(context, "tenant@mcbar.dev", algorithm: "bogus", flags: [:no_password])
+    end
+  end
+
+  describe "Key export" do
+    test "export a public key", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+
+      {:ok, exported} = ExGpgme.export_keys(context, [key])
+      assert String.starts_with?(exported, "-----BEGIN PGP PUBLIC KEY BLOCK-----")
+
+      {:ok, minimal} = ExGpgme.export_keys(context, "foo@mcbar.dev", [:minimal])
+      assert byte_size(minimal) <= byte_size(exported)
+    end
+
+    test "export a secret key without armor", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], armor: false)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+
+      {:ok, exported} =
+        ExGpgme.export_keys(context, "foo@mcbar.dev", [:secret, passphrase: passphrase])
+
+      refute String.valid?(exported)
+
+      {:ok, other} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, %{secret_considered: 1}} = ExGpgme.import_key(other, exported)
+    end
+
+    test "export all keys", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, _} = ExGpgme.import_key(context, File.read!("test/data/boaty_mcboatface/public.asc"))
+
+      {:ok, exported} = ExGpgme.export_all(context)
+      {:ok, %{considered: 2}} = ExGpgme.import_key(context, exported)
+    end
+
+    test "refuses to export an empty list of keys", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.export_keys(context, [], [:secret])
+    end
+
+    test "rejects unknown modes", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:error, %{code: :invalid_value, source: :user}} = ExGpgme.export_all(context, [:bogus])
+    end
+  end
+
+  describe "Key deletion" do
+    test "delete a public key", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, _} = ExGpgme.import_key(context, File.read!("test/data/boaty_mcboatface/public.asc"))
+      {:ok, key} = ExGpgme.find_key(context, "BB6700D8CFF4EDA5E6E233093722D688D77C1C10")
+
+      :ok = ExGpgme.delete_key(context, key)
+
+      {:error, %{code: :not_found}} =
+        ExGpgme.find_key(context, "BB6700D8CFF4EDA5E6E233093722D688D77C1C10")
+    end
+
+    test "refuse to delete a secret key unless allowed", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      fingerprint = "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"
+
+      {:error, %{code: :conflict}} = ExGpgme.delete_key(context, fingerprint)
+      {:ok, _} = ExGpgme.find_key(context, fingerprint)
+
+      :ok = ExGpgme.delete_key(context, fingerprint, [:allow_secret, :force])
+      {:error, %{code: :not_found}} = ExGpgme.find_key(context, fingerprint)
+    end
+
+    test "missing key", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+
+      {:error, %{code: :not_found}} =
+        ExGpgme.delete_key(context, "BB6700D8CFF4EDA5E6E233093722D688D77C1C10")
+    end
+
+    test "refuse to delete a list of keys", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      fingerprint = "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.delete_key(context, [fingerprint, fingerprint])
+
+      {:ok, _} = ExGpgme.find_key(context, fingerprint)
+    end
+  end
+
+  describe "Key signing" do
+    setup ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, signer} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      {:ok, _} = ExGpgme.add_signer(context, signer)
+      {:ok, _} = ExGpgme.import_key(context, File.read!("test/data/boaty_mcboatface/public.asc"))
+      {:ok, key} = ExGpgme.find_key(context, "BB6700D8CFF4EDA5E6E233093722D688D77C1C10")
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, context: context, signer: signer, key: key, passphrase: passphrase}
+    end
+
+    test "certify and revoke", %{context: context, signer: signer, key: key, passphrase: passphrase} do
+      {:ok, key} = ExGpgme.sign_key(context, key, :all, expires: 86_400, passphrase: passphrase)
+
+      [%{signatures: signatures}] = key.user_ids
+      assert Enum.any?(signatures, &(&1.signer_key == signer.id and &1.exportable))
+
+      {:ok, key} = ExGpgme.revoke_signature(context, key, signer, passphrase: passphrase)
+
+      [%{signatures: signatures}] = key.user_ids
+      assert Enum.any?(signatures, &(&1.signer_key == signer.id and &1.revoked))
+    end
+
+    test "local signature on chosen user ids", %{context: context, key: key, passphrase: passphrase} do
+      [%{name: name, email: email}] = key.user_ids
+      user_id = "#{name} <#{email}>"
+
+      {:ok, key} = ExGpgme.sign_key(context, key, [user_id], local: true, passphrase: passphrase)
+
+      [%{signatures: signatures}] = key.user_ids
+      assert Enum.any?(signatures, &(&1.exportable == false))
+    end
+
+    test "unknown user id", %{context: context, key: key, passphrase: passphrase} do
+      {:error, _} = ExGpgme.sign_key(context, key, ["nobody@mcbar.dev"], passphrase: passphrase)
+    end
+  end
+
+  describe "Key maintenance" do
+    setup ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, context: context, key: key, passphrase: passphrase}
+    end
+
+    test "set expiration", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, key} = ExGpgme.set_expire(context, key, :never, passphrase: passphrase)
+      [%{expiration_time: :unknown}, _] = key.subkeys
+
+      [_, subkey] = key.subkeys
+
+      {:ok, key} =
+        ExGpgme.set_expire(context, key, 86_400, subkeys: [subkey], passphrase: passphrase)
+
+      [_, %{expiration_time: expiration_time}] = key.subkeys
+      assert is_integer(expiration_time)
+    end
+
+    test "rejects a zero expiration", %{context: context, key: key, passphrase: passphrase} do
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.set_expire(context, key, 0, passphrase: passphrase)
+    end
+
+    test "add, make primary and revoke user ids", %{
+      context: context,
+      key: key,
+      passphrase: passphrase
+    } do
+      user_id = "Foo McBar <foo@work.mcbar.dev>"
+
+      {:ok, key} = ExGpgme.add_uid(context, key, user_id, passphrase: passphrase)
+      assert Enum.any?(key.user_ids, &(&1.email == "foo@work.mcbar.dev"))
+
+      {:ok, key} = ExGpgme.set_primary_uid(context, key, user_id, passphrase: passphrase)
+      [%{email: "foo@work.mcbar.dev"} | _] = key.user_ids
+
+      {:ok, key} = ExGpgme.revoke_uid(context, key, user_id, passphrase: passphrase)
+      assert Enum.any?(key.user_ids, &(&1.email == "foo@work.mcbar.dev" and &1.revoked))
+    end
+
+    test "revoke key", %{context: context, key: key, passphrase: passphrase} = ctx do
+      {:ok, certificate, key} =
+        ExGpgme.revoke_key(context, key,
+          reason: :superseded,
+          description: "Replaced by a new key",
+          passphrase: passphrase
+        )
+
+      assert key.is_revoked
+      assert String.starts_with?(certificate, "-----BEGIN PGP PUBLIC KEY BLOCK-----")
+
+      other_home = Path.join(ctx[:gnupg_home], "other")
+      File.mkdir_p!(other_home)
+      File.chmod!(other_home, 0o700)
+      {:ok, other} = ExGpgme.create(path: other_home)
+      {:ok, _} = ExGpgme.import_key(other, File.read!("test/data/foo_mcbar/public.asc"))
+      {:ok, %{considered: 1}} = ExGpgme.import_key(other, certificate)
+
+      {:ok, key} = ExGpgme.find_key(other, "foo@mcbar.dev")
+      assert key.is_revoked
+      assert key.fingerprint == "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"
+    end
+
+    test "rejects unknown revocation reasons", %{context: context, key: key} do
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.revoke_key(context, key, reason: :bored)
+    end
+  end
+
+  describe "Owner trust" do
+    test "set and read owner trust", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      assert ExGpgme.Key.owner_trust(key) == :unknown
+
+      {:ok, key} = ExGpgme.set_owner_trust(context, key, :marginal)
+      assert ExGpgme.Key.owner_trust(key) == :marginal
+    end
+
+    test "ultimately trusted keys are valid recipients", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+
+      {:error, %{code: :unusable_pubkey}} = ExGpgme.encrypt(context, key, "untrusted")
+      {:ok, key} = ExGpgme.set_owner_trust(context, key, :ultimate)
+      {:ok, _} = ExGpgme.encrypt(context, key, "trusted")
+    end
+
+    test "rejects unknown trust levels", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.set_owner_trust(context, key, :blind)
+    end
+
+    test "undefined and unknown owner trust", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      {:ok, key} = ExGpgme.set_owner_trust(context, key, :marginal)
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.set_owner_trust(context, key, :unknown)
+
+      {:ok, key} = ExGpgme.set_owner_trust(context, key, :undefined)
+      refute ExGpgme.Key.owner_trust(key) == :marginal
+    end
+  end
+
+  describe "TOFU" do
+    setup ctx do
+      File.write!(Path.join(ctx[:gnupg_home], "gpg.conf"), "trust-model tofu+pgp\n")
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      {:ok, context: context, key: key}
+    end
+
+    test "list TOFU information", %{context: context} do
+      [key] = ExGpgme.list_keys(context, mode: [:local, :with_tofu])
+      [%ExGpgme.UserId{tofu_info: %ExGpgme.TofuInfo{} = info}] = key.user_ids
+
+      assert is_integer(info.signature_count)
+      assert is_integer(info.encrypted_count)
+    end
+
+    test "without the listing mode", %{key: key} do
+      [%ExGpgme.UserId{tofu_info: :none}] = key.user_ids
+    end
+
+    test "set policy", %{context: context, key: key} do
+      {:ok, key} = ExGpgme.set_tofu_policy(context, key, :bad)
+      [%ExGpgme.UserId{tofu_info: %ExGpgme.TofuInfo{policy: :bad}}] = key.user_ids
+
+      {:ok, key} = ExGpgme.set_tofu_policy(context, key, :good)
+      [%ExGpgme.UserId{tofu_info: %ExGpgme.TofuInfo{policy: :good}}] = key.user_ids
+    end
+
+    test "rejects unknown policies", %{context: context, key: key} do
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.set_tofu_policy(context, key, :maybe)
+    end
+  end
+
+  describe "Key location" do
+    setup ctx do
+      # A stand-in keyserver answering every HKP lookup with boaty's key.
+      root = Path.join(ctx[:gnupg_home], "keyserver")
+      File.mkdir_p!(Path.join(root, "pks"))
+      File.cp!("test/data/boaty_mcboatface/public.asc", Path.join([root, "pks", "lookup"]))
+
+      :ok = Application.ensure_started(:inets)
+
+      {:ok, httpd} =
+        :inets.start(:httpd,
+          port: 0,
+          bind_address: {127, 0, 0, 1},
+          server_name: 'localhost',
+          server_root: String.to_charlist(root),
+          document_root: String.to_charlist(root)
+        )
+
+      on_exit(fn -> :inets.stop(:httpd, httpd) end)
+
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      keyserver = "hkp://127.0.0.1:#{:httpd.info(httpd)[:port]}"
+      {:ok, context: context, keyserver: keyserver}
+    end
+
+    test "locate a key on a keyserver", %{context: context, keyserver: keyserver} do
+      {:error, %{code: :not_found}} = ExGpgme.find_key(context, "boaty@mcboatface.dev")
+
+      {:ok, key} =
+        ExGpgme.locate_key(context, "boaty@mcboatface.dev", mechanisms: [:local, keyserver])
+
+      assert key.fingerprint == "BB6700D8CFF4EDA5E6E233093722D688D77C1C10"
+      assert key.origin == :key_server
+      {:ok, _} = ExGpgme.find_key(context, "boaty@mcboatface.dev")
+    end
+
+    test "locate a local key", %{context: context} do
+      {:ok, key} = ExGpgme.locate_key(context, "foo@mcbar.dev", mechanisms: [:local])
+      assert key.fingerprint == "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"
+    end
+
+    test "skip the keyring unless asked to", %{context: context, keyserver: keyserver} do
+      # The stand-in only knows boaty's key, so foo's key can only come
+      # from the keyring.
+      {:error, %{code: :not_found}} =
+        ExGpgme.locate_key(context, "foo@mcbar.dev", mechanisms: [keyserver])
+
+      {:ok, key} =
+        ExGpgme.locate_key(context, "foo@mcbar.dev", mechanisms: [keyserver, :local])
+
+      assert key.fingerprint == "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"
+    end
+
+    test "fall back when WKD fails", %{context: context, keyserver: keyserver} do
+      # mcboatface.dev publishes no Web Key Directory.
+      {:ok, key} =
+        ExGpgme.locate_key(context, "boaty@mcboatface.dev", mechanisms: [:wkd, keyserver])
+
+      assert key.fingerprint == "BB6700D8CFF4EDA5E6E233093722D688D77C1C10"
+      assert key.origin == :key_server
+    end
+
+    test "unknown address", %{context: context} do
+      {:error, %{code: :not_found}} =
+        ExGpgme.locate_key(context, "nobody@mcbar.dev", mechanisms: [:local])
     end
   end
 
@@ -360,7 +861,7 @@ defmodule ExGpgme.Test do
       {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
       {:ok, :none} = ExGpgme.sender(context)
       sender = "foo"
-      {:error, :not_found} = ExGpgme.set_sender(context, sender)
+      {:error, %{code: :invalid_value}} = ExGpgme.set_sender(context, sender)
       {:ok, :none} = ExGpgme.sender(context)
     end
   end
@@ -432,6 +933,359 @@ defmodule ExGpgme.Test do
     end
   end
 
+  describe "Signing" do
+    setup ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      {:ok, _} = ExGpgme.add_signer(context, key)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, context: context, key: key, passphrase: passphrase}
+    end
+
+    test "normal", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, signature, %{new_signatures: [new_signature]}} =
+        ExGpgme.sign(context, "hello", passphrase: passphrase)
+
+      assert String.starts_with?(signature, "-----BEGIN PGP MESSAGE-----")
+      assert new_signature.fingerprint == key.fingerprint
+    end
+
+    test "detached", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, signature, %{new_signatures: [new_signature]}} =
+        ExGpgme.sign(context, "hello", mode: :detached, passphrase: passphrase)
+
+      assert String.starts_with?(signature, "-----BEGIN PGP SIGNATURE-----")
+      assert new_signature.fingerprint == key.fingerprint
+    end
+
+    test "clear", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, signature, %{new_signatures: [new_signature]}} =
+        ExGpgme.sign(context, "hello", mode: :clear, passphrase: passphrase)
+
+      assert String.starts_with?(signature, "-----BEGIN PGP SIGNED MESSAGE-----")
+      assert String.contains?(signature, "hello")
+      assert new_signature.fingerprint == key.fingerprint
+    end
+
+    test "includes signature notations", %{context: context, passphrase: passphrase} do
+      :ok = ExGpgme.add_signature_notation(context, "foo@mcbar.dev", "bar", [:human_readable])
+
+      {:ok, signature, %{invalid_signers: []}} =
+        ExGpgme.sign(context, "hello", mode: :detached, passphrase: passphrase)
+
+      {:ok, :none, [signature]} = ExGpgme.verify(context, signature, signed_text: "hello")
+      [%{name: "foo@mcbar.dev", value: "bar"}] = signature.notations
+    end
+  end
+
+  describe "Verification" do
+    setup ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      {:ok, _} = ExGpgme.add_signer(context, key)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, context: context, key: key, passphrase: passphrase}
+    end
+
+    test "clear-signed", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, signed, _} = ExGpgme.sign(context, "hello\n", mode: :clear, passphrase: passphrase)
+      {:ok, "hello\n", [signature]} = ExGpgme.verify(context, signed)
+
+      assert signature.fingerprint == key.fingerprint
+      assert signature.status == :ok
+    end
+
+    test "detached", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, signed, _} = ExGpgme.sign(context, "hello", mode: :detached, passphrase: passphrase)
+      {:ok, :none, [signature]} = ExGpgme.verify(context, signed, signed_text: "hello")
+
+      assert signature.fingerprint == key.fingerprint
+      assert signature.status == :ok
+    end
+
+    test "carries signature notations", %{context: context, passphrase: passphrase} do
+      :ok = ExGpgme.add_signature_notation(context, "foo@mcbar.dev", "bar", [:human_readable])
+      {:ok, signed, _} = ExGpgme.sign(context, "hello", passphrase: passphrase)
+      {:ok, "hello", [signature]} = ExGpgme.verify(context, signed)
+
+      [%{name: "foo@mcbar.dev", value: "bar", human_readable: true}] = signature.notations
+    end
+
+    test "tampered data", %{context: context, passphrase: passphrase} do
+      {:ok, signed, _} = ExGpgme.sign(context, "hello", mode: :detached, passphrase: passphrase)
+      {:ok, :none, [signature]} = ExGpgme.verify(context, signed, signed_text: "hullo")
+
+      assert signature.status == :bad_signature
+      refute :valid in signature.summary
+    end
+
+    test "require validity", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, signed, _} = ExGpgme.sign(context, "hello", passphrase: passphrase)
+
+      {:error, %{code: :untrusted_signature}} =
+        ExGpgme.verify(context, signed, require_validity: :full)
+
+      {:ok, _} = ExGpgme.set_owner_trust(context, key, :ultimate)
+      {:ok, "hello", [_]} = ExGpgme.verify(context, signed, require_validity: :full)
+    end
+
+    test "require validity of tampered data", %{context: context, key: key} = ctx do
+      {:ok, _} = ExGpgme.set_owner_trust(context, key, :ultimate)
+
+      {:ok, signed, _} =
+        ExGpgme.sign(context, "hello", mode: :detached, passphrase: ctx[:passphrase])
+
+      {:error, %{code: :untrusted_signature}} =
+        ExGpgme.verify(context, signed, signed_text: "hullo", require_validity: :marginal)
+    end
+
+    test "rejects unknown validity levels", %{context: context, passphrase: passphrase} do
+      {:ok, signed, _} = ExGpgme.sign(context, "hello", passphrase: passphrase)
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.verify(context, signed, require_validity: :never)
+    end
+  end
+
+  describe "Certificate import" do
+    test "import PEM and DER certificates", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], protocol: :cms)
+      assert context.protocol == :cms
+      [] = ExGpgme.list_keys(context)
+
+      [{:Certificate, der, _}] =
+        "test/data/test_ca/certificate.pem" |> File.read!() |> :public_key.pem_decode()
+
+      {:ok, %{imported: 1}} = ExGpgme.import_key(context, der)
+
+      pem = File.read!("test/data/foo_mcbar/certificate.pem")
+      {:ok, %{imported: 1}} = ExGpgme.import_key(context, pem)
+
+      assert length(ExGpgme.list_keys(context)) == 2
+    end
+
+    test "import a PKCS#12 bundle", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], protocol: :cms)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      bundle = File.read!("test/data/foo_mcbar/certificate.p12")
+
+      {:ok, %{secret_imported: 1}} = ExGpgme.import_pkcs12(context, bundle, passphrase)
+
+      [%{fingerprint: "838DB8B4436E70C3643A2D98D2E1D08049FF9633"}] =
+        ExGpgme.list_keys(context, secret_only: true)
+    end
+  end
+
+  describe "CMS" do
+    @certificate "838DB8B4436E70C3643A2D98D2E1D08049FF9633"
+
+    setup ctx do
+      :ok = Test.GpgmeCase.setup_gpgsm(ctx[:gnupg_home])
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], protocol: :cms)
+      {:ok, key} = ExGpgme.find_key(context, @certificate)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, context: context, key: key, passphrase: passphrase}
+    end
+
+    test "list certificates", %{context: context} do
+      certificates = ExGpgme.list_keys(context)
+      assert length(certificates) == 2
+      assert Enum.all?(certificates, &(&1.protocol == :cms))
+
+      [%{fingerprint: @certificate, has_secret: true}] =
+        ExGpgme.list_keys(context, secret_only: true)
+    end
+
+    test "certificate details", %{key: key} do
+      %ExGpgme.Key{
+        protocol: :cms,
+        issuer_serial: "1001",
+        issuer_name: "CN=ExGpgme Test CA,O=ExGpgme Test,C=DE",
+        chain_id: "2E6F97363736AC6BDDFFD6342904FCECF5492250",
+        card_key: false,
+        card_serial_number: :none
+      } = key
+
+      [subject, %{uid: "<foo@mcbar.dev>", dn: :none}] = key.user_ids
+
+      assert subject.dn == [
+               {"EMail", "foo@mcbar.dev"},
+               {"CN", "Foo McBar"},
+               {"O", "ExGpgme Test"},
+               {"C", "DE"}
+             ]
+    end
+
+    test "OpenPGP user ids have no DN", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      [%{dn: :none}] = key.user_ids
+    end
+
+    test "certificate chain", %{context: context, key: key} do
+      {:ok, [^key, root]} = ExGpgme.certificate_chain(context, key)
+      assert root.fingerprint == "2E6F97363736AC6BDDFFD6342904FCECF5492250"
+      assert root.chain_id == root.fingerprint
+      [%{dn: [{"CN", "ExGpgme Test CA"} | _]}] = root.user_ids
+
+      {:ok, [^root]} = ExGpgme.certificate_chain(context, root)
+    end
+
+    test "sign and verify", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, _} = ExGpgme.add_signer(context, key)
+      {:ok, signed, %{new_signatures: [_]}} = ExGpgme.sign(context, "hello", passphrase: passphrase)
+      assert String.starts_with?(signed, "-----BEGIN SIGNED MESSAGE-----")
+
+      {:ok, "hello", [signature]} = ExGpgme.verify(context, signed)
+      assert signature.fingerprint == @certificate
+      assert signature.status == :ok
+    end
+
+    test "detached signature", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, _} = ExGpgme.add_signer(context, key)
+
+      {:ok, signature, _} =
+        ExGpgme.sign(context, "hello", mode: :detached, passphrase: passphrase)
+
+      {:ok, :none, [%{status: :ok}]} = ExGpgme.verify(context, signature, signed_text: "hello")
+
+      {:ok, :none, [%{status: :bad_signature}]} =
+        ExGpgme.verify(context, signature, signed_text: "hullo")
+    end
+
+    test "export a PKCS#12 bundle", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, context} = ExGpgme.set_armor(context, false)
+      {:ok, bundle} = ExGpgme.export_pkcs12(context, key, passphrase)
+      assert byte_size(bundle) > 0
+
+      {:ok, %{secret_considered: 1}} = ExGpgme.import_pkcs12(context, bundle, passphrase)
+    end
+
+    test "encrypt and decrypt", %{context: context, key: key, passphrase: passphrase} do
+      {:ok, cipher_text} = ExGpgme.encrypt(context, key, "hello")
+      assert String.starts_with?(cipher_text, "-----BEGIN ENCRYPTED MESSAGE-----")
+
+      {:ok, "hello"} = ExGpgme.decrypt(context, passphrase, cipher_text)
+    end
+  end
+
+  describe "Passphrase provider" do
+    defmodule Attempts do
+      @behaviour ExGpgme.PassphraseProvider
+
+      def init({test, passphrases}), do: {:ok, {test, passphrases}}
+
+      def handle_passphrase_request(request, {test, []}) do
+        send(test, {:request, request})
+        {:cancel, {test, []}}
+      end
+
+      def handle_passphrase_request(request, {test, [passphrase | rest]}) do
+        send(test, {:request, request})
+        {:ok, passphrase, {test, rest}}
+      end
+    end
+
+    setup ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar.dev")
+      {:ok, cipher_text} = ExGpgme.encrypt(context, key, "hello", [:always_trust])
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, context: context, cipher_text: cipher_text, passphrase: passphrase}
+    end
+
+    test "decrypt", %{context: context, cipher_text: cipher_text, passphrase: passphrase} do
+      {:ok, provider} = ExGpgme.PassphraseProvider.start_link(Attempts, {self(), [passphrase]})
+      :ok = ExGpgme.set_passphrase_provider(context, provider)
+
+      assert {:ok, "hello"} = ExGpgme.decrypt(context, :provider, cipher_text)
+      assert_received {:request, %{uid_hint: "728052F947BD30B8 " <> _, prev_was_bad: false}}
+    end
+
+    test "retry after a bad passphrase", %{
+      context: context,
+      cipher_text: cipher_text,
+      passphrase: passphrase
+    } do
+      {:ok, provider} =
+        ExGpgme.PassphraseProvider.start_link(Attempts, {self(), ["wrong", passphrase]})
+
+      :ok = ExGpgme.set_passphrase_provider(context, provider)
+
+      assert {:ok, "hello"} = ExGpgme.decrypt(context, :provider, cipher_text)
+      assert_received {:request, %{prev_was_bad: false}}
+      assert_received {:request, %{prev_was_bad: true}}
+    end
+
+    test "reply with any binary", %{
+      context: context,
+      cipher_text: cipher_text,
+      passphrase: passphrase
+    } do
+      {:ok, provider} =
+        ExGpgme.PassphraseProvider.start_link(Attempts, {self(), [<<0xFF, 0xFE>>, passphrase]})
+
+      :ok = ExGpgme.set_passphrase_provider(context, provider)
+
+      assert {:ok, "hello"} = ExGpgme.decrypt(context, :provider, cipher_text)
+      assert_received {:request, %{prev_was_bad: true}}
+    end
+
+    test "cancel", %{context: context, cipher_text: cipher_text} do
+      {:ok, provider} = ExGpgme.PassphraseProvider.start_link(Attempts, {self(), []})
+      :ok = ExGpgme.set_passphrase_provider(context, provider)
+
+      assert {:error, %{code: :canceled}} = ExGpgme.decrypt(context, :provider, cipher_text)
+    end
+
+    test "time out", %{context: context, cipher_text: cipher_text} do
+      :ok = ExGpgme.set_passphrase_provider(context, self(), timeout: 100)
+
+      assert {:error, %{code: :timeout}} = ExGpgme.decrypt(context, :provider, cipher_text)
+      assert_received {:gpgme_passphrase_request, _reply, %{prev_was_bad: false}}
+    end
+
+    test "fixed passphrases take precedence", %{
+      context: context,
+      cipher_text: cipher_text,
+      passphrase: passphrase
+    } do
+      {:ok, provider} = ExGpgme.PassphraseProvider.start_link(Attempts, {self(), []})
+      :ok = ExGpgme.set_passphrase_provider(context, provider)
+
+      assert {:ok, "hello"} = ExGpgme.decrypt(context, passphrase, cipher_text)
+      refute_received {:request, _}
+    end
+
+    test "empty passphrases are not routed to the provider", %{
+      context: context,
+      cipher_text: cipher_text
+    } do
+      {:ok, provider} = ExGpgme.PassphraseProvider.start_link(Attempts, {self(), []})
+      :ok = ExGpgme.set_passphrase_provider(context, provider)
+
+      assert {:error, _} = ExGpgme.decrypt(context, "", cipher_text)
+      refute_received {:request, _}
+    end
+
+    test "unknown provider", %{context: context} do
+      assert {:error, %{code: :not_found, source: :ex_gpgme}} =
+               ExGpgme.set_passphrase_provider(context, :no_such_provider)
+    end
+
+    test "clear", %{context: context, cipher_text: cipher_text} do
+      :ok = ExGpgme.set_passphrase_provider(context, self())
+      :ok = ExGpgme.clear_passphrase_provider(context)
+
+      assert {:error, %{code: :no_passphrase}} = ExGpgme.decrypt(context, :provider, cipher_text)
+      refute_received {:gpgme_passphrase_request, _, _}
+    end
+
+    test "no provider", %{context: context, cipher_text: cipher_text} do
+      assert {:error, %{code: :no_passphrase}} = ExGpgme.decrypt(context, :provider, cipher_text)
+    end
+  end
+
   describe "Encryption/Decryption" do
     alias ExGpgme.Key
 
@@ -453,7 +1307,7 @@ defmodule ExGpgme.Test do
       Foo
       """
 
-      {:ok, cipher_text} = ExGpgme.encrypt(context, key, message)
+      {:ok, cipher_text} = ExGpgme.encrypt(context, key, message, [:always_trust])
 
       assert String.starts_with?(cipher_text, "-----BEGIN PGP MESSAGE-----")
       passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
@@ -462,6 +1316,90 @@ defmodule ExGpgme.Test do
       assert message == decrypted
     end
 
+    test "should encrypt for multiple recipients with hidden key ids", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+
+      private_key = File.read!("test/data/boaty_mcboatface/private.asc")
+      {:ok, _} = ExGpgme.import_key(context, private_key)
+
+      {:ok, foo} = ExGpgme.find_key(context, "foo@mcbar")
+      recipients = [foo, "BB6700D8CFF4EDA5E6E233093722D688D77C1C10"]
+
+      {:ok, cipher_text} =
+        ExGpgme.encrypt(context, recipients, "for both", [:always_trust, :throw_keyids])
+
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, "for both", result} = ExGpgme.decrypt_verify(context, passphrase, cipher_text)
+
+      assert length(result.recipients) == 2
+      assert Enum.all?(result.recipients, &(&1.key_id == "0000000000000000"))
+    end
+
+    test "should not encrypt for untrusted keys by default", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      {:error, %{code: :unusable_pubkey}} = ExGpgme.encrypt(context, key, "untrusted")
+    end
+
+    test "should sign and encrypt, then decrypt and verify", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      {:ok, _} = ExGpgme.add_signer(context, key)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+
+      message = "Signed and sealed."
+
+      {:ok, cipher_text, %{new_signatures: [_]}} =
+        ExGpgme.encrypt_sign(context, key, message, [:always_trust, passphrase: passphrase])
+
+      assert String.starts_with?(cipher_text, "-----BEGIN PGP MESSAGE-----")
+
+      {:ok, ^message, result} = ExGpgme.decrypt_verify(context, passphrase, cipher_text)
+
+      assert result.unsigned == false
+      assert is_binary(result.symmetric_algorithm)
+      assert [%{status: :ok}] = result.recipients
+      assert [%{fingerprint: fingerprint, status: :ok}] = result.signatures
+      assert fingerprint == key.fingerprint
+    end
+
+    test "should report unsigned messages on decrypt and verify", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+
+      {:ok, cipher_text} = ExGpgme.encrypt(context, key, "not signed", [:always_trust])
+      {:ok, "not signed", result} = ExGpgme.decrypt_verify(context, passphrase, cipher_text)
+
+      assert result.unsigned == true
+      assert result.signatures == []
+    end
+
+    test "should encrypt/decrypt binary data without armor", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], armor: false)
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+
+      blob = :crypto.strong_rand_bytes(4096)
+
+      {:ok, cipher_text} = ExGpgme.encrypt(context, key, blob, [:always_trust])
+      refute String.valid?(cipher_text)
+
+      {:ok, ^blob} = ExGpgme.decrypt(context, passphrase, cipher_text)
+    end
+
+    test "should sign and verify binary data without armor", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], armor: false)
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      {:ok, _} = ExGpgme.add_signer(context, key)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+
+      blob = <<0, 1, 2, 255, 254, 253>>
+
+      {:ok, signature, _} = ExGpgme.sign(context, blob, mode: :detached, passphrase: passphrase)
+      {:ok, :none, [%{status: :ok}]} = ExGpgme.verify(context, signature, signed_text: blob)
+    end
+
     test "should encrypt/decrypt data with symmetric key", ctx do
       {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
 
@@ -486,4 +1424,192 @@ defmodule ExGpgme.Test do
       assert message == decrypted
     end
   end
+
+  describe "Streaming" do
+    setup ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], armor: false)
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      {:ok, context: context, key: key, passphrase: passphrase}
+    end
+
+    test "encrypt and decrypt an enumerable", %{context: context, key: key, passphrase: passphrase} do
+      chunks = Enum.map(1..64, fn _ -> :crypto.strong_rand_bytes(16_384) end)
+
+      cipher_text =
+        context
+        |> ExGpgme.encrypt_stream(key, chunks, [:always_trust])
+        |> Enum.to_list()
+        |> IO.iodata_to_binary()
+
+      plaintext =
+        context
+        |> ExGpgme.decrypt_stream(passphrase, [cipher_text])
+        |> Enum.to_list()
+        |> IO.iodata_to_binary()
+
+      assert plaintext == IO.iodata_to_binary(chunks)
+    end
+
+    test "encrypt a file", %{context: context, key: key, passphrase: passphrase} = ctx do
+      path = Path.join(ctx[:gnupg_home], "plain.bin")
+      data = :crypto.strong_rand_bytes(200_000)
+      File.write!(path, data)
+
+      cipher_path = Path.join(ctx[:gnupg_home], "plain.bin.gpg")
+
+      context
+      |> ExGpgme.encrypt_stream(key, path, [:always_trust])
+      |> Stream.into(File.stream!(cipher_path))
+      |> Stream.run()
+
+      plaintext =
+        context
+        |> ExGpgme.decrypt_stream(passphrase, cipher_path)
+        |> Enum.join()
+
+      assert plaintext == data
+    end
+
+    test "stop early", %{context: context, key: key} do
+      chunks = Stream.repeatedly(fn -> :crypto.strong_rand_bytes(16_384) end)
+
+      [_chunk] =
+        context
+        |> ExGpgme.encrypt_stream(key, chunks, [:always_trust])
+        |> Enum.take(1)
+
+      {:ok, _} = ExGpgme.encrypt(context, key, "still usable", [:always_trust])
+    end
+
+    test "use the context while streaming", %{context: context, key: key} do
+      chunks = Stream.repeatedly(fn -> :crypto.strong_rand_bytes(16_384) end)
+
+      [_, _] =
+        context
+        |> ExGpgme.encrypt_stream(key, chunks, [:always_trust])
+        |> Stream.each(fn _ ->
+          {:ok, _} = ExGpgme.encrypt(context, key, "meanwhile", [:always_trust])
+        end)
+        |> Enum.take(2)
+    end
+
+    test "output much larger than the input", %{
+      context: context,
+      key: key,
+      passphrase: passphrase
+    } do
+      data = :binary.copy(<<0>>, 8_000_000)
+      {:ok, cipher_text} = ExGpgme.encrypt(context, key, data, [:always_trust])
+      assert byte_size(cipher_text) < 100_000
+
+      plaintext =
+        context
+        |> ExGpgme.decrypt_stream(passphrase, [cipher_text])
+        |> Enum.join()
+
+      assert plaintext == data
+    end
+
+    test "raises on errors", %{context: context} do
+      assert_raise ExGpgme.Error, fn ->
+        context
+        |> ExGpgme.decrypt_stream("", ["not a message"])
+        |> Stream.run()
+      end
+    end
+  end
+
+  describe "Files" do
+    setup ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home], armor: false)
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      {:ok, _} = ExGpgme.add_signer(context, key)
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+      path = Path.join(ctx[:gnupg_home], "plain.bin")
+      File.write!(path, :crypto.strong_rand_bytes(200_000))
+      {:ok, context: context, key: key, passphrase: passphrase, path: path}
+    end
+
+    test "encrypt and decrypt", %{context: context, key: key, passphrase: passphrase, path: path} do
+      :ok = ExGpgme.encrypt_file(context, key, path, path <> ".gpg", [:always_trust])
+      :ok = ExGpgme.decrypt_file(context, passphrase, path <> ".gpg", path <> ".out")
+
+      assert File.read!(path <> ".out") == File.read!(path)
+    end
+
+    test "sign and verify", %{context: context, key: key, passphrase: passphrase, path: path} do
+      {:ok, %{new_signatures: [_]}} =
+        ExGpgme.sign_file(context, path, path <> ".sig", passphrase: passphrase)
+
+      {:ok, [signature]} = ExGpgme.verify_file(context, path <> ".sig", path <> ".out")
+
+      assert signature.fingerprint == key.fingerprint
+      assert signature.status == :ok
+      assert File.read!(path <> ".out") == File.read!(path)
+    end
+
+    test "detached signature", %{context: context, passphrase: passphrase, path: path} do
+      {:ok, _} =
+        ExGpgme.sign_file(context, path, path <> ".sig", mode: :detached, passphrase: passphrase)
+
+      {:ok, [%{status: :ok}]} =
+        ExGpgme.verify_file(context, path <> ".sig", nil, signed_file: path)
+    end
+
+    test "removes the destination on errors", %{context: context, path: path} do
+      {:error, _} = ExGpgme.decrypt_file(context, "", path, path <> ".out")
+      refute File.exists?(path <> ".out")
+    end
+
+    test "keeps existing files", %{context: context, passphrase: passphrase, path: path} do
+      File.write!(path <> ".out", "precious")
+
+      {:error, %{code: :already_exists}} =
+        ExGpgme.decrypt_file(context, passphrase, path, path <> ".out")
+
+      assert File.read!(path <> ".out") == "precious"
+    end
+
+    test "rejects writing over the source", %{context: context, key: key, path: path} do
+      contents = File.read!(path)
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.encrypt_file(context, key, path, path, [:always_trust])
+
+      assert File.read!(path) == contents
+    end
+
+    test "missing source", %{context: context, key: key, path: path} do
+      assert {:error, %{code: :not_found}} =
+               ExGpgme.encrypt_file(context, key, path <> ".missing", path <> ".gpg")
+    end
+  end
+
+  describe "Async" do
+    test "encrypt and decrypt", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.find_key(context, "foo@mcbar")
+      passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+
+      ref = ExGpgme.async(:encrypt, [context, key, "in the background", [:always_trust]])
+      assert_receive {:gpgme_result, ^ref, {:ok, cipher_text}}, 5_000
+
+      ref = ExGpgme.async(:decrypt, [context, passphrase, cipher_text])
+      assert_receive {:gpgme_result, ^ref, {:ok, "in the background"}}, 5_000
+    end
+
+    test "delivers errors", ctx do
+      {:ok, context} = ExGpgme.create(path: ctx[:gnupg_home])
+
+      ref = ExGpgme.async(:find_key, [context, "Dr. Baz"])
+      assert_receive {:gpgme_result, ^ref, {:error, %{code: :not_found}}}, 5_000
+    end
+
+    test "delivers crashes" do
+      ref = ExGpgme.async(:decrypt, [:not_a_context, "", "data"])
+      assert_receive {:gpgme_result, ^ref, {:error, %{source: :ex_gpgme, message: message}}}
+      assert message =~ "FunctionClauseError"
+    end
+  end
 end
@@ -9,8 +9,17 @@ defmodule ExGpgme.Bindings.Test do
       assert is_reference(context)
     end
 
+    test "should create with options", ctx do
+      {:ok, context} =
+        ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home], armor: false, offline: true)
+
+      {:ok, %{armor: false, offline: true, pinentry_mode: :default}} =
+        ExGpgme.Native.context_options(context)
+    end
+
     test "should fail on unsupported protocol", ctx do
-      :unsupported_protocol = ExGpgme.Native.context_create(:foobar, ctx[:gnupg_home])
+      {:error, %{code: :invalid_value, source: :user, message: "unsupported protocol"}} =
+        ExGpgme.Native.context_create(:foobar, ctx[:gnupg_home])
     end
 
     test "should allow creating multiple contexts " do
@@ -129,11 +138,43 @@ defmodule ExGpgme.Bindings.Test do
       data = "hello this is my message"
 
       {:ok, cipher} =
-        ExGpgme.Native.context_encrypt(context, "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8", data)
+        ExGpgme.Native.context_encrypt(
+          context,
+          "",
+          ["D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"],
+          data,
+          [:always_trust]
+        )
 
       assert String.starts_with?(cipher, "-----BEGIN PGP MESSAGE-----")
     end
 
+    test "should not encrypt to untrusted key without always_trust", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+
+      {:error, %{code: :unusable_pubkey}} =
+        ExGpgme.Native.context_encrypt(
+          context,
+          "",
+          ["D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"],
+          "hello",
+          []
+        )
+    end
+
+    test "should fail on unsupported encrypt flag", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.Native.context_encrypt(
+          context,
+          "",
+          ["D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"],
+          "hello",
+          [:foobar]
+        )
+    end
+
     test "should decrypt", ctx do
       {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
 
@@ -150,8 +191,10 @@ defmodule ExGpgme.Bindings.Test do
       {:ok, cipher_text} =
         ExGpgme.Native.context_encrypt(
           context,
-          "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8",
-          data
+          "",
+          ["D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"],
+          data,
+          [:always_trust]
         )
 
       assert String.starts_with?(cipher_text, "-----BEGIN PGP MESSAGE-----")
@@ -166,6 +209,50 @@ defmodule ExGpgme.Bindings.Test do
       assert data == decrypted
     end
 
+    test "should toggle armor", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+      {:ok, true} = ExGpgme.Native.context_armor(context)
+      :ok = ExGpgme.Native.context_set_armor(context, false)
+      {:ok, false} = ExGpgme.Native.context_armor(context)
+    end
+
+    test "should encrypt and decrypt non-UTF-8 data", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+      :ok = ExGpgme.Native.context_set_armor(context, false)
+
+      data = <<0, 159, 146, 150, 255, 254>>
+
+      {:ok, cipher_text} =
+        ExGpgme.Native.context_encrypt(
+          context,
+          "",
+          ["D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"],
+          data,
+          [:always_trust]
+        )
+
+      refute String.starts_with?(cipher_text, "-----BEGIN PGP MESSAGE-----")
+
+      {:ok, ^data} = ExGpgme.Native.context_decrypt(context, "6c616829565def2c", cipher_text)
+    end
+
+    test "should return structured error on garbage ciphertext", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+
+      {:error, %{code: :no_data, source: source, message: message}} =
+        ExGpgme.Native.context_decrypt(context, "6c616829565def2c", "not a message")
+
+      assert is_atom(source)
+      assert is_binary(message)
+    end
+
+    test "should return structured error for unknown recipient", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+
+      {:error, %{code: :not_found}} =
+        ExGpgme.Native.context_encrypt(context, "", ["Dr. Baz"], "hello", [:always_trust])
+    end
+
     test "should find key by email", ctx do
       {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
       {:ok, ref} = ExGpgme.Native.context_find_key(context, "foo@mcbar")
@@ -180,14 +267,109 @@ defmodule ExGpgme.Bindings.Test do
 
     test "should not find key wth bogus name", ctx do
       {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
-      :not_found = ExGpgme.Native.context_find_key(context, "Dr. Baz")
+      {:error, %{code: :not_found, source: :gpgme, message: message}} =
+        ExGpgme.Native.context_find_key(context, "Dr. Baz")
+
+      assert is_binary(message)
+    end
+
+    test "should sign", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.Native.context_find_key(context, "foo@mcbar")
+      :ok = ExGpgme.Native.context_add_signer(context, key)
+
+      {:ok, signature, result} =
+        ExGpgme.Native.context_sign(context, "6c616829565def2c", :detached, "hello")
+
+      assert String.starts_with?(signature, "-----BEGIN PGP SIGNATURE-----")
+      assert result.invalid_signers == []
+      [new_signature] = result.new_signatures
+      assert new_signature.fingerprint == "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"
+      assert new_signature.key_algorithm == "RSA"
+      assert is_binary(new_signature.hash_algorithm)
+      assert is_integer(new_signature.class)
+      assert is_integer(new_signature.creation_time)
+    end
+
+    test "should not sign with unsupported mode", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.Native.context_sign(context, "6c616829565def2c", :foobar, "hello")
     end
 
     # test "should encrypt (symmetric)"
     # test "should decrypt (symmetric)"
 
-    # test "should sign key"
-    # test "should verify"
+    test "should verify", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.Native.context_find_key(context, "foo@mcbar")
+      :ok = ExGpgme.Native.context_add_signer(context, key)
+
+      {:ok, signed, _result} =
+        ExGpgme.Native.context_sign(context, "6c616829565def2c", :normal, "hello")
+
+      {:ok, "hello", [signature]} = ExGpgme.Native.context_verify(context, signed)
+
+      assert signature.fingerprint == "D1DBB4E18FF6FA6AFA040B07728052F947BD30B8"
+      assert signature.status == :ok
+      assert is_list(signature.summary)
+      assert is_atom(signature.validity)
+      assert is_integer(signature.creation_time)
+      assert signature.expiration_time == :none
+      assert signature.key_algorithm == "RSA"
+      assert signature.wrong_key_usage == false
+      assert signature.tofu_conflict == false
+      assert signature.notations == []
+    end
+
+    test "should verify detached", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.Native.context_find_key(context, "foo@mcbar")
+      :ok = ExGpgme.Native.context_add_signer(context, key)
+
+      {:ok, signature, _result} =
+        ExGpgme.Native.context_sign(context, "6c616829565def2c", :detached, "hello")
+
+      {:ok, :none, [%{status: :ok}]} =
+        ExGpgme.Native.context_verify_detached(context, signature, "hello")
+
+      {:ok, :none, [%{status: :bad_signature, summary: summary}]} =
+        ExGpgme.Native.context_verify_detached(context, signature, "goodbye")
+
+      assert :red in summary
+    end
+
+    test "should sign key", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+      {:ok, signer} = ExGpgme.Native.context_find_key(context, "foo@mcbar")
+      :ok = ExGpgme.Native.context_add_signer(context, signer)
+
+      {:ok, _} =
+        ExGpgme.Native.context_import(
+          context,
+          File.read!("test/data/boaty_mcboatface/public.asc")
+        )
+
+      {:ok, key} =
+        ExGpgme.Native.context_find_key(context, "BB6700D8CFF4EDA5E6E233093722D688D77C1C10")
+
+      {:ok, signed} =
+        ExGpgme.Native.context_sign_key(context, "6c616829565def2c", key, [], 0, [:local])
+
+      {:ok, [%{signatures: signatures}]} = ExGpgme.Native.key_user_ids(signed)
+
+      assert %{signer_key: "728052F947BD30B8", exportable: false, revoked: false} =
+               Enum.find(signatures, &(&1.signer_key == "728052F947BD30B8"))
+    end
+
+    test "should not sign key with unsupported flag", ctx do
+      {:ok, context} = ExGpgme.Native.context_create(:openpgp, ctx[:gnupg_home])
+      {:ok, key} = ExGpgme.Native.context_find_key(context, "foo@mcbar")
+
+      {:error, %{code: :invalid_value, source: :user}} =
+        ExGpgme.Native.context_sign_key(context, "", key, [], 0, [:foobar])
+    end
   end
 
   describe "Keys" do
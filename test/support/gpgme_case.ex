@@ -98,6 +98,34 @@ defmodule Test.GpgmeCase do
     :ok
   end
 
+  @doc """
+  Seed the gpgsm part of a test home: trust the local test CA, skip CRL
+  checks and import foo_mcbar's certificate along with its secret key.
+  """
+  def setup_gpgsm(dir) do
+    ca_path = Path.expand("test/data/test_ca/certificate.pem")
+    [{:Certificate, ca, _}] = ca_path |> File.read!() |> :public_key.pem_decode()
+    ca_fingerprint = :sha |> :crypto.hash(ca) |> Base.encode16()
+
+    :ok = dir |> Path.join("gpgsm.conf") |> File.write!("disable-crl-checks\n")
+    :ok = dir |> Path.join("trustlist.txt") |> File.write!("#{ca_fingerprint} S relax\n")
+
+    gpgsm = System.get_env("GPGSM") || System.find_executable("gpgsm")
+    passphrase = Application.get_env(:ex_gpgme, :test_passphrase)
+    certificate_path = Path.expand("test/data/foo_mcbar/certificate.p12")
+
+    %Result{status: 0} = Porcelain.shell("#{gpgsm} --batch --import #{ca_path}")
+
+    %Result{status: 0} =
+      Porcelain.shell(
+        "echo #{passphrase} | #{gpgsm} --batch --pinentry-mode loopback --passphrase-fd 0 --import #{
+          certificate_path
+        }"
+      )
+
+    :ok
+  end
+
   def agent_conf(_dir, pinentry) do
     """
     ignore-invalid-option allow-loopback-pinentry